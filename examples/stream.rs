@@ -0,0 +1,10 @@
+use futures_lite::StreamExt;
+
+fn main() {
+    let mut stream = darkmode::stream().unwrap();
+    async_io::block_on(async {
+        while let Some(mode) = stream.next().await {
+            println!("{mode:?}");
+        }
+    });
+}
@@ -0,0 +1,264 @@
+use crate::portal::{Connection, Setting, SettingChanged, NAMESPACE};
+use crate::{Client, Error, Mode, Subscription, Value};
+
+/// All settings of the `org.freedesktop.appearance` namespace.
+///
+/// Settings that are missing or have an unexpected type keep their default.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Appearance {
+    /// The preferred color scheme.
+    pub color_scheme: Mode,
+    /// The accent color, `None` if the user did not set one.
+    pub accent_color: Option<AccentColor>,
+    /// The preferred contrast.
+    pub contrast: Contrast,
+    /// The preferred amount of motion.
+    pub motion_preference: MotionPreference,
+}
+
+impl Appearance {
+    pub(crate) fn read(connection: &Connection) -> Result<Self, Error> {
+        let mut appearance = Self::default();
+        if let Some(settings) = connection.read_all(&[NAMESPACE])?.get(NAMESPACE) {
+            for (key, value) in settings {
+                appearance.set(key, value);
+            }
+        }
+        Ok(appearance)
+    }
+
+    /// Sets the field for `key`, keeping it if `value` has an unexpected type.
+    fn set(&mut self, key: &str, value: &Value) {
+        fn set<T: Setting>(field: &mut T, value: &Value) {
+            if let Some(value) = T::from_value(value) {
+                *field = value;
+            }
+        }
+
+        match key {
+            Mode::KEY => set(&mut self.color_scheme, value),
+            <Option<AccentColor>>::KEY => set(&mut self.accent_color, value),
+            Contrast::KEY => set(&mut self.contrast, value),
+            MotionPreference::KEY => set(&mut self.motion_preference, value),
+            _ => {}
+        }
+    }
+
+    /// Returns whether this changed the [`Appearance`].
+    ///
+    /// Errors when a setting changed to a value of an unexpected type.
+    pub(crate) fn update(&mut self, setting_changed: &SettingChanged) -> Result<bool, Error> {
+        fn update<T: Setting + PartialEq>(
+            field: &mut T,
+            setting_changed: &SettingChanged,
+        ) -> Result<bool, Error> {
+            match setting_changed.get().transpose()? {
+                Some(value) if value != *field => {
+                    *field = value;
+                    Ok(true)
+                }
+                _ => Ok(false),
+            }
+        }
+
+        // At most one of them is for the changed key.
+        Ok(update(&mut self.color_scheme, setting_changed)?
+            | update(&mut self.accent_color, setting_changed)?
+            | update(&mut self.contrast, setting_changed)?
+            | update(&mut self.motion_preference, setting_changed)?)
+    }
+}
+
+/// The accent color preferred by the user.
+///
+/// The channels are in the sRGB color space and range from `0.0` to `1.0`.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct AccentColor {
+    /// Red channel.
+    pub red: f64,
+    /// Green channel.
+    pub green: f64,
+    /// Blue channel.
+    pub blue: f64,
+}
+
+impl AccentColor {
+    /// Converts to 8-bit `[red, green, blue]`.
+    #[must_use]
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [channel(self.red), channel(self.green), channel(self.blue)]
+    }
+
+    /// Converts to a hex color, e.g., `#3584e4`.
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        let [red, green, blue] = self.to_rgb8();
+        format!("#{red:02x}{green:02x}{blue:02x}")
+    }
+}
+
+impl From<AccentColor> for [u8; 3] {
+    fn from(value: AccentColor) -> Self {
+        value.to_rgb8()
+    }
+}
+
+/// `None` when no accent color is set, which the portal signals with values
+/// out of range.
+impl Setting for Option<AccentColor> {
+    const EXPECTED: &'static str = "(ddd)";
+    const KEY: &'static str = "accent-color";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let Value::Struct(fields) = value else {
+            return None;
+        };
+        let [Value::F64(red), Value::F64(green), Value::F64(blue)] = fields[..] else {
+            return None;
+        };
+        Some(
+            [red, green, blue]
+                .iter()
+                .all(|channel| (0.0..=1.0).contains(channel))
+                .then_some(AccentColor { red, green, blue }),
+        )
+    }
+}
+
+/// The contrast preferred by the user.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+#[repr(u32)]
+pub enum Contrast {
+    /// No preference.
+    #[default]
+    NoPreference,
+    /// Prefers high contrast.
+    High,
+}
+
+impl Setting for Contrast {
+    const EXPECTED: &'static str = "u32";
+    const KEY: &'static str = "contrast";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        Some(match value.as_u32()? {
+            1 => Contrast::High,
+            _ => Contrast::NoPreference,
+        })
+    }
+}
+
+/// The amount of motion in animations preferred by the user.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+#[repr(u32)]
+pub enum MotionPreference {
+    /// No preference.
+    #[default]
+    NoPreference,
+    /// Prefers reduced motion, e.g., disabled or simpler animations.
+    Reduced,
+}
+
+impl Setting for MotionPreference {
+    const EXPECTED: &'static str = "u32";
+    const KEY: &'static str = "reduced-motion";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        Some(match value.as_u32()? {
+            1 => MotionPreference::Reduced,
+            _ => MotionPreference::NoPreference,
+        })
+    }
+}
+
+/// Detects the current [`AccentColor`], `None` if the user did not set one.
+///
+/// # Errors
+///
+/// Errors when the session bus or the portal cannot be reached, or the portal
+/// does not support `accent-color`.
+pub fn accent_color() -> Result<Option<AccentColor>, Error> {
+    Client::global()?.accent_color()
+}
+
+/// Calls `call_back` with the current [`AccentColor`] and every time it
+/// changes, see [`subscribe`](crate::subscribe).
+///
+/// # Errors
+///
+/// Errors when the session bus or the portal cannot be reached, or the portal
+/// does not support `accent-color`.
+pub fn subscribe_accent_color(
+    call_back: impl FnMut(Option<AccentColor>) + Send + 'static,
+) -> Result<Subscription, Error> {
+    Client::global()?.subscribe_accent_color(call_back)
+}
+
+/// Detects the current [`Contrast`].
+///
+/// # Errors
+///
+/// Errors when the session bus or the portal cannot be reached, or the portal
+/// does not support `contrast`.
+pub fn detect_contrast() -> Result<Contrast, Error> {
+    Client::global()?.detect_contrast()
+}
+
+/// Calls `call_back` with the current [`Contrast`] and every time it changes,
+/// see [`subscribe`](crate::subscribe).
+///
+/// # Errors
+///
+/// Errors when the session bus or the portal cannot be reached, or the portal
+/// does not support `contrast`.
+pub fn subscribe_contrast(
+    call_back: impl FnMut(Contrast) + Send + 'static,
+) -> Result<Subscription, Error> {
+    Client::global()?.subscribe_contrast(call_back)
+}
+
+/// Detects the current [`MotionPreference`].
+///
+/// # Errors
+///
+/// Errors when the session bus or the portal cannot be reached, or the portal
+/// does not support `reduced-motion`.
+pub fn detect_motion_preference() -> Result<MotionPreference, Error> {
+    Client::global()?.detect_motion_preference()
+}
+
+/// Calls `call_back` with the current [`MotionPreference`] and every time it
+/// changes, see [`subscribe`](crate::subscribe).
+///
+/// # Errors
+///
+/// Errors when the session bus or the portal cannot be reached, or the portal
+/// does not support `reduced-motion`.
+pub fn subscribe_motion_preference(
+    call_back: impl FnMut(MotionPreference) + Send + 'static,
+) -> Result<Subscription, Error> {
+    Client::global()?.subscribe_motion_preference(call_back)
+}
+
+/// Detects the current [`Appearance`] with a single call to the portal.
+///
+/// # Errors
+///
+/// Errors when the session bus or the portal cannot be reached.
+pub fn appearance() -> Result<Appearance, Error> {
+    Client::global()?.appearance()
+}
+
+/// Calls `call_back` with the current [`Appearance`] and every time any of
+/// its fields change, see [`subscribe`](crate::subscribe).
+///
+/// # Errors
+///
+/// Errors when the session bus or the portal cannot be reached.
+pub fn subscribe_appearance(
+    call_back: impl FnMut(Appearance) + Send + 'static,
+) -> Result<Subscription, Error> {
+    Client::global()?.subscribe_appearance(call_back)
+}
@@ -0,0 +1,216 @@
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+use crate::{Client, DconfBackend, Error, GtkBackend, KdeBackend, Mode, Subscription};
+
+/// Source of the [`Mode`], tried in order by a [`Detector`].
+///
+/// ```no_run
+/// use darkmode::{Backend, Detector, Error, Mode, Subscription};
+///
+/// struct Night;
+///
+/// impl Backend for Night {
+///     fn name(&self) -> &str {
+///         "night"
+///     }
+///
+///     fn detect(&self) -> Result<Mode, Error> {
+///         Ok(Mode::Dark)
+///     }
+///
+///     fn watch(&self, mut call_back: Box<dyn FnMut(Mode) + Send>) -> Result<Subscription, Error> {
+///         call_back(Mode::Dark);
+///         Ok(Subscription::from_stop(|| Ok(())))
+///     }
+/// }
+///
+/// let detector = Detector::default().backend(Night);
+/// let detection = detector.detect()?;
+/// println!("{:?} from {}", detection.mode, detection.backend);
+/// # Ok::<_, darkmode::Error>(())
+/// ```
+pub trait Backend: Send + Sync {
+    /// Name identifying the backend in a [`Detection`].
+    fn name(&self) -> &str;
+
+    /// Detects the current [`Mode`], [`Mode::Default`] if there is no
+    /// preference.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the mode cannot be read. When the backend is not available,
+    /// e.g., because the desktop it reads is not used, the error should be one
+    /// for which [`Error::is_unavailable`] holds, e.g.,
+    /// [`Error::BackendUnavailable`], so a [`Detector`] prefers reporting the
+    /// errors of other backends.
+    fn detect(&self) -> Result<Mode, Error>;
+
+    /// Calls `call_back` with the current [`Mode`] and every time it changes,
+    /// until the returned [`Subscription`] is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the backend is not available.
+    fn watch(&self, call_back: Box<dyn FnMut(Mode) + Send>) -> Result<Subscription, Error>;
+}
+
+/// [`Backend`] reading the mode from the portal, see [`Client::detect`].
+#[derive(Debug, Clone, Default)]
+pub struct PortalBackend {
+    client: Option<Client>,
+}
+
+impl PortalBackend {
+    /// Uses the [`global`](Client::global) client.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `client` instead of the [`global`](Client::global) one.
+    #[must_use]
+    pub fn client(mut self, client: &Client) -> Self {
+        self.client = Some(client.clone());
+        self
+    }
+
+    fn with_client<T>(&self, f: impl FnOnce(&Client) -> Result<T, Error>) -> Result<T, Error> {
+        match &self.client {
+            Some(client) => f(client),
+            None => f(Client::global()?),
+        }
+    }
+}
+
+impl Backend for PortalBackend {
+    fn name(&self) -> &'static str {
+        "portal"
+    }
+
+    fn detect(&self) -> Result<Mode, Error> {
+        self.with_client(Client::detect)
+    }
+
+    fn watch(&self, call_back: Box<dyn FnMut(Mode) + Send>) -> Result<Subscription, Error> {
+        self.with_client(|client| client.subscribe(call_back))
+    }
+}
+
+/// Ordered chain of [`Backend`]s, the first one with a preference decides the
+/// [`Mode`].
+///
+/// A backend reporting [`Mode::Default`] or failing is skipped. When none has
+/// a preference, the mode is [`Mode::Default`] from the first backend that
+/// did not fail, and only when all fail is an error returned.
+///
+/// The [default](Detector::default) chain is the one used by
+/// [`detect`](crate::detect) and [`subscribe`](crate::subscribe), without
+/// their override through the environment.
+#[derive(Clone)]
+#[must_use]
+pub struct Detector {
+    backends: Vec<Arc<dyn Backend>>,
+}
+
+/// [`Mode`] detected by a [`Detector`], with the [`Backend`] that detected it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Detection<'a> {
+    /// The detected mode.
+    pub mode: Mode,
+    /// The [`name`](Backend::name) of the backend.
+    pub backend: &'a str,
+}
+
+impl Default for Detector {
+    /// Returns the chain of [`PortalBackend`], [`DconfBackend`],
+    /// [`KdeBackend`] and [`GtkBackend`].
+    fn default() -> Self {
+        Self::new()
+            .backend(PortalBackend::new())
+            .backend(DconfBackend)
+            .backend(KdeBackend)
+            .backend(GtkBackend)
+    }
+}
+
+impl Debug for Detector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.backends.iter().map(|backend| backend.name()))
+            .finish()
+    }
+}
+
+impl Detector {
+    /// Returns a detector without backends, add them with
+    /// [`backend`](Self::backend).
+    pub fn new() -> Self {
+        Self {
+            backends: Vec::new(),
+        }
+    }
+
+    /// Appends `backend` to the chain.
+    pub fn backend(mut self, backend: impl Backend + 'static) -> Self {
+        self.backends.push(Arc::new(backend));
+        self
+    }
+
+    /// Detects the current [`Mode`] with the first backend that has a
+    /// preference.
+    ///
+    /// # Errors
+    ///
+    /// Errors when all backends fail, with the first error for which
+    /// [`Error::is_unavailable`] does not hold, or else the first one.
+    pub fn detect(&self) -> Result<Detection<'_>, Error> {
+        self.choose().map(|(backend, mode)| Detection {
+            mode,
+            backend: backend.name(),
+        })
+    }
+
+    /// Calls `call_back` with the current [`Mode`] and every time it changes,
+    /// watching the backend that [`detect`](Self::detect) chooses.
+    ///
+    /// # Errors
+    ///
+    /// Errors when all backends fail, with the first error for which
+    /// [`Error::is_unavailable`] does not hold, or else the first one.
+    pub fn subscribe(
+        &self,
+        call_back: impl FnMut(Mode) + Send + 'static,
+    ) -> Result<Subscription, Error> {
+        let (backend, _) = self.choose()?;
+        backend.watch(Box::new(call_back))
+    }
+
+    fn choose(&self) -> Result<(&dyn Backend, Mode), Error> {
+        let mut readable = None;
+        let mut error: Option<Error> = None;
+        for backend in &self.backends {
+            match backend.detect() {
+                Ok(Mode::Default) => {
+                    readable.get_or_insert(&**backend);
+                }
+                Ok(mode) => return Ok((&**backend, mode)),
+                // A backend actually failing tells more than one that is not
+                // available.
+                Err(e) => match &error {
+                    Some(first) if first.is_unavailable() && !e.is_unavailable() => {
+                        error = Some(e);
+                    }
+                    Some(_) => {}
+                    None => error = Some(e),
+                },
+            }
+        }
+        match (readable, error) {
+            (Some(backend), _) => Ok((backend, Mode::Default)),
+            (None, Some(error)) => Err(error),
+            (None, None) => Err(Error::NoBackend),
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use std::fmt::{self, Debug};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::portal::{Config, Connection, Filter, Setting, NAMESPACE};
+#[cfg(all(feature = "stream", unix))]
+use crate::stream::ModeStream;
+use crate::subscription::{subscribe_setting, subscribe_with};
+use crate::{
+    AccentColor, Appearance, Contrast, Error, Event, Mode, MotionPreference, Settings, Subscription,
+};
+
+static GLOBAL: OnceLock<Client> = OnceLock::new();
+
+/// Connection to the portal, shared by all calls made through it.
+///
+/// With `zbus`, subscriptions and streams share it as well. With `libdbus`,
+/// only calls to the portal do, every subscription and stream opens its own
+/// connection to receive signals, as `libdbus` cannot share incoming messages
+/// between consumers.
+///
+/// The free functions, e.g., [`detect`](crate::detect) and
+/// [`subscribe`](crate::subscribe), use the [`global`](Self::global) client.
+/// Creating a separate one is only necessary to control the connection's
+/// lifetime, or to configure it using [`Client::builder`].
+#[derive(Clone)]
+pub struct Client {
+    connection: Connection,
+}
+
+impl Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client").finish_non_exhaustive()
+    }
+}
+
+impl Client {
+    /// Connects to the portal on the session bus.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the session bus cannot be reached.
+    pub fn new() -> Result<Self, Error> {
+        Self::builder().build()
+    }
+
+    /// Returns a [`ClientBuilder`] to configure the connection.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// Returns the client shared by the whole process, connecting on first
+    /// use.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the session bus cannot be reached, connecting is retried on
+    /// the next call.
+    pub fn global() -> Result<&'static Self, Error> {
+        if let Some(client) = GLOBAL.get() {
+            return Ok(client);
+        }
+        let client = Self::new()?;
+        Ok(GLOBAL.get_or_init(|| client))
+    }
+
+    /// Makes this the [`global`](Self::global) client, used by the free
+    /// functions.
+    ///
+    /// # Errors
+    ///
+    /// Returns `self` when the global client was already initialized.
+    pub fn set_global(self) -> Result<(), Self> {
+        GLOBAL.set(self)
+    }
+
+    pub(crate) fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Returns a [`Settings`] client for arbitrary settings using this
+    /// connection.
+    #[must_use]
+    pub fn settings(&self) -> Settings {
+        Settings::with_connection(self.connection.clone())
+    }
+
+    /// Detects the current [`Mode`], see [`detect`](crate::detect).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached.
+    pub fn detect(&self) -> Result<Mode, Error> {
+        Mode::read(&self.connection)
+    }
+
+    /// Calls `call_back` with the current [`Mode`] and every time it changes,
+    /// see [`subscribe`](crate::subscribe).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached.
+    pub fn subscribe(
+        &self,
+        call_back: impl FnMut(Mode) + Send + 'static,
+    ) -> Result<Subscription, Error> {
+        subscribe_setting(&self.connection, call_back)
+    }
+
+    /// Calls `call_back` with the current [`Mode`] and every time it changes,
+    /// and with the [`Event`]s of the subscription, see
+    /// [`subscribe_events`](crate::subscribe_events).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached.
+    pub fn subscribe_events(
+        &self,
+        call_back: impl FnMut(Event) + Send + 'static,
+    ) -> Result<Subscription, Error> {
+        Subscription::builder()
+            .client(self)
+            .deduplicate(false)
+            .subscribe_events(call_back)
+    }
+
+    /// Returns a stream of the current [`Mode`] and every change to it, see
+    /// [`stream`](crate::stream()).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached.
+    #[cfg(all(feature = "stream", unix))]
+    pub fn stream(&self) -> Result<ModeStream, Error> {
+        let changes = self.connection.stream(&Filter::of::<Mode>())?;
+        Ok(ModeStream::new(Mode::read(&self.connection)?, changes))
+    }
+
+    /// Detects the current [`AccentColor`], see
+    /// [`accent_color`](crate::accent_color).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached, or does not support
+    /// `accent-color`.
+    pub fn accent_color(&self) -> Result<Option<AccentColor>, Error> {
+        Option::read(&self.connection)
+    }
+
+    /// Calls `call_back` with the current [`AccentColor`] and every time it
+    /// changes, see [`subscribe_accent_color`](crate::subscribe_accent_color).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached, or does not support
+    /// `accent-color`.
+    pub fn subscribe_accent_color(
+        &self,
+        call_back: impl FnMut(Option<AccentColor>) + Send + 'static,
+    ) -> Result<Subscription, Error> {
+        subscribe_setting(&self.connection, call_back)
+    }
+
+    /// Detects the current [`Contrast`], see
+    /// [`detect_contrast`](crate::detect_contrast).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached, or does not support
+    /// `contrast`.
+    pub fn detect_contrast(&self) -> Result<Contrast, Error> {
+        Contrast::read(&self.connection)
+    }
+
+    /// Calls `call_back` with the current [`Contrast`] and every time it
+    /// changes, see [`subscribe_contrast`](crate::subscribe_contrast).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached, or does not support
+    /// `contrast`.
+    pub fn subscribe_contrast(
+        &self,
+        call_back: impl FnMut(Contrast) + Send + 'static,
+    ) -> Result<Subscription, Error> {
+        subscribe_setting(&self.connection, call_back)
+    }
+
+    /// Detects the current [`MotionPreference`], see
+    /// [`detect_motion_preference`](crate::detect_motion_preference).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached, or does not support
+    /// `reduced-motion`.
+    pub fn detect_motion_preference(&self) -> Result<MotionPreference, Error> {
+        MotionPreference::read(&self.connection)
+    }
+
+    /// Calls `call_back` with the current [`MotionPreference`] and every time
+    /// it changes, see
+    /// [`subscribe_motion_preference`](crate::subscribe_motion_preference).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached, or does not support
+    /// `reduced-motion`.
+    pub fn subscribe_motion_preference(
+        &self,
+        call_back: impl FnMut(MotionPreference) + Send + 'static,
+    ) -> Result<Subscription, Error> {
+        subscribe_setting(&self.connection, call_back)
+    }
+
+    /// Detects the current [`Appearance`], see
+    /// [`appearance`](crate::appearance()).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached.
+    pub fn appearance(&self) -> Result<Appearance, Error> {
+        Appearance::read(&self.connection)
+    }
+
+    /// Calls `call_back` with the current [`Appearance`] and every time any of
+    /// its fields change, see
+    /// [`subscribe_appearance`](crate::subscribe_appearance).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached.
+    pub fn subscribe_appearance(
+        &self,
+        call_back: impl FnMut(Appearance) + Send + 'static,
+    ) -> Result<Subscription, Error> {
+        subscribe_with(
+            &self.connection,
+            Filter::namespace(NAMESPACE),
+            Appearance::read,
+            Appearance::update,
+            call_back,
+        )
+    }
+}
+
+/// Builder for a [`Client`], created by [`Client::builder`].
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct ClientBuilder {
+    config: Config,
+}
+
+impl ClientBuilder {
+    /// Sets the timeout of calls to the portal, defaults to 100 ms.
+    ///
+    /// When the portal is not running yet, the first call needs to wait for
+    /// the bus to activate it, which can take longer during session startup.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    /// Connects to the bus at `address`, e.g., `unix:path=/run/user/1000/bus`,
+    /// instead of the session bus.
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.config.address = Some(address.into());
+        self
+    }
+
+    /// Sets the bus name of the portal, defaults to
+    /// `org.freedesktop.portal.Desktop`.
+    pub fn destination(mut self, destination: impl Into<String>) -> Self {
+        self.config.destination = destination.into();
+        self
+    }
+
+    /// Sets the object path of the portal, defaults to
+    /// `/org/freedesktop/portal/desktop`.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.config.path = path.into();
+        self
+    }
+
+    /// Connects to the bus.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the bus cannot be reached.
+    pub fn build(self) -> Result<Client, Error> {
+        Ok(Client {
+            connection: Connection::new(self.config)?,
+        })
+    }
+
+    /// Uses an existing `libdbus` connection instead of connecting to the bus.
+    ///
+    /// Calls to the portal go through `connection`, but subscriptions and
+    /// streams still receive signals on their own connections, as `libdbus`
+    /// cannot share incoming messages between consumers. These connect to the
+    /// configured [`address`](Self::address) or the session bus, so set the
+    /// address of `connection`'s bus if that is a different one.
+    ///
+    /// Only available with the `libdbus` backend, i.e., without the `zbus`
+    /// feature, also when another crate enables it, see the
+    /// [crate documentation](crate).
+    #[cfg(all(feature = "dbus", not(feature = "zbus")))]
+    #[must_use]
+    pub fn build_with_dbus(self, connection: dbus::blocking::Connection) -> Client {
+        Client {
+            connection: Connection::with_connection(connection, self.config),
+        }
+    }
+
+    /// Uses an existing `zbus` connection instead of connecting to the bus.
+    ///
+    /// All calls and subscriptions share `connection`, the configured
+    /// [`address`](Self::address) is unused. Neither is the
+    /// [`timeout`](Self::timeout), as `zbus` configures it on the connection.
+    #[cfg(feature = "zbus")]
+    #[must_use]
+    pub fn build_with_zbus(self, connection: zbus::blocking::Connection) -> Client {
+        Client {
+            connection: Connection::with_connection(connection, self.config),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Fallback reading the GNOME setting from the user's dconf database, used
+//! when the portal is not available.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::fallback::{self, Fallback};
+use crate::{Backend, Error, Mode, Subscription};
+
+mod gvdb;
+
+use gvdb::Gvdb;
+
+/// Path of the user's database, relative to `$XDG_CONFIG_HOME`.
+const DATABASE: &str = "dconf/user";
+/// Key of `color-scheme` in the `org.gnome.desktop.interface` schema.
+const KEY: &str = "/org/gnome/desktop/interface/color-scheme";
+
+/// [`Backend`] reading GNOME's `color-scheme` from the user's dconf database,
+/// `$XDG_CONFIG_HOME/dconf/user`, watching it for changes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DconfBackend;
+
+impl Backend for DconfBackend {
+    fn name(&self) -> &'static str {
+        "dconf"
+    }
+
+    fn detect(&self) -> Result<Mode, Error> {
+        FALLBACK.detect()
+    }
+
+    fn watch(&self, call_back: Box<dyn FnMut(Mode) + Send>) -> Result<Subscription, Error> {
+        FALLBACK.watch(call_back)
+    }
+}
+
+/// Reads the user's database, `$XDG_CONFIG_HOME/dconf/user`.
+const FALLBACK: Fallback = Fallback { paths, read };
+
+fn paths() -> Result<Vec<PathBuf>, Error> {
+    Ok(vec![fallback::config_home(DATABASE)?.join(DATABASE)])
+}
+
+fn read(paths: &[PathBuf]) -> Result<Mode, Error> {
+    let path = &paths[0];
+    let data = fs::read(path).map_err(|error| Error::config(path, error))?;
+    let variant = Gvdb::new(&data)
+        .and_then(|gvdb| gvdb.get(KEY))
+        .map_err(|error| Error::config(path, error))?;
+    let Some(variant) = variant else {
+        return Ok(Mode::Default);
+    };
+    match gvdb::variant_str(variant) {
+        Some("prefer-dark") => Ok(Mode::Dark),
+        Some("prefer-light") => Ok(Mode::Light),
+        Some(_) => Ok(Mode::Default),
+        None => Err(Error::UnexpectedValue {
+            expected: "string",
+            found: None,
+        }),
+    }
+}
@@ -0,0 +1,118 @@
+//! Reader for `GVariant` databases, the format of dconf's database files.
+//!
+//! Only lookups by a full key in the root table are supported, by scanning all
+//! items instead of using the hash table, as the database of a single user is
+//! small.
+
+use std::io;
+
+const HASH_HEADER: usize = 8;
+const ITEM: usize = 24;
+const NO_PARENT: u32 = u32::MAX;
+
+pub(crate) struct Gvdb<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+    /// Items of the root table.
+    items: &'a [u8],
+}
+
+impl<'a> Gvdb<'a> {
+    /// Errors when `data` is not a `GVariant` database.
+    pub(crate) fn new(data: &'a [u8]) -> io::Result<Self> {
+        let big_endian = match data.get(..8) {
+            Some(b"GVariant") => false,
+            Some(b"raVGtnai") => true,
+            _ => return Err(invalid("not a GVariant database")),
+        };
+        let mut gvdb = Self {
+            data,
+            big_endian,
+            items: &[],
+        };
+        let root = gvdb.slice(gvdb.u32(data, 16)?, gvdb.u32(data, 20)?)?;
+        // The upper 5 bits are the shift of the bloom filter.
+        let bloom_words = gvdb.u32(root, 0)? & ((1 << 27) - 1);
+        let buckets = gvdb.u32(root, 4)?;
+        let items = (bloom_words as usize + buckets as usize)
+            .checked_mul(4)
+            .and_then(|hash| root.get(HASH_HEADER + hash..))
+            .ok_or_else(|| invalid("hash table out of bounds"))?;
+        gvdb.items = items;
+        Ok(gvdb)
+    }
+
+    /// Returns the serialized `GVariant` of type `v` stored for `key`.
+    pub(crate) fn get(&self, key: &str) -> io::Result<Option<&'a [u8]>> {
+        for index in 0..self.items.len() / ITEM {
+            let item = &self.items[index * ITEM..][..ITEM];
+            if item[14] == b'v' && self.name(index)? == key.as_bytes() {
+                return self
+                    .slice(self.u32(item, 16)?, self.u32(item, 20)?)
+                    .map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the full name of the item at `index`, which is the names of its
+    /// parents followed by its key.
+    fn name(&self, mut index: usize) -> io::Result<Vec<u8>> {
+        let mut parts = Vec::new();
+        // Bounds the depth, in case the parents form a cycle.
+        for _ in 0..=self.items.len() / ITEM {
+            let item = self
+                .items
+                .get(index * ITEM..)
+                .and_then(|items| items.get(..ITEM))
+                .ok_or_else(|| invalid("parent out of bounds"))?;
+            let start = self.u32(item, 8)?;
+            let size = [item[12], item[13]];
+            let size = if self.big_endian {
+                u16::from_be_bytes(size)
+            } else {
+                u16::from_le_bytes(size)
+            };
+            parts.push(self.slice(start, start.saturating_add(size.into()))?);
+            match self.u32(item, 4)? {
+                NO_PARENT => return Ok(parts.into_iter().rev().flatten().copied().collect()),
+                parent => index = parent as usize,
+            }
+        }
+        Err(invalid("cyclic parents"))
+    }
+
+    fn slice(&self, start: u32, end: u32) -> io::Result<&'a [u8]> {
+        self.data
+            .get(start as usize..end as usize)
+            .ok_or_else(|| invalid("pointer out of bounds"))
+    }
+
+    fn u32(&self, bytes: &[u8], offset: usize) -> io::Result<u32> {
+        let bytes = bytes
+            .get(offset..offset + 4)
+            .ok_or_else(|| invalid("unexpected end of data"))?;
+        let bytes = bytes.try_into().expect("slice has 4 bytes");
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+}
+
+/// Returns the string in a serialized `GVariant` of type `v`, or `None` if it
+/// holds another type.
+pub(crate) fn variant_str(variant: &[u8]) -> Option<&str> {
+    // The child's data is followed by a nul byte and its type.
+    let separator = variant.iter().rposition(|&byte| byte == 0)?;
+    if &variant[separator + 1..] != b"s" {
+        return None;
+    }
+    let string = variant[..separator].strip_suffix(b"\0")?;
+    std::str::from_utf8(string).ok()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
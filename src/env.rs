@@ -0,0 +1,29 @@
+//! Overrides of the mode through environment variables.
+
+use std::env;
+
+use crate::Mode;
+
+/// Returns the mode forced by `DARKMODE`, or else by a `GTK_THEME` ending in
+/// `:dark`.
+pub(crate) fn mode() -> Option<Mode> {
+    darkmode().or_else(gtk_theme)
+}
+
+fn darkmode() -> Option<Mode> {
+    let value = env::var("DARKMODE").ok()?;
+    match value.trim().to_ascii_lowercase().as_str() {
+        "dark" => Some(Mode::Dark),
+        "light" => Some(Mode::Light),
+        "default" => Some(Mode::Default),
+        _ => None,
+    }
+}
+
+fn gtk_theme() -> Option<Mode> {
+    // GTK only knows the `dark` variant, a theme without it need not be light.
+    env::var("GTK_THEME")
+        .ok()?
+        .ends_with(":dark")
+        .then_some(Mode::Dark)
+}
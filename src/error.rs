@@ -0,0 +1,185 @@
+use std::fmt::{self, Display};
+use std::io;
+use std::path::PathBuf;
+
+use crate::portal::UNKNOWN_METHOD;
+use crate::Value;
+
+/// Error returned when communicating with the portal, or reading a fallback,
+/// fails.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The session bus, or the bus at the configured
+    /// [`address`](crate::ClientBuilder::address), cannot be reached, e.g.,
+    /// because there is no `DBUS_SESSION_BUS_ADDRESS`.
+    NoSessionBus(Box<dyn std::error::Error + Send + Sync>),
+    /// The portal is not running or does not provide the settings interface.
+    PortalUnavailable(Box<dyn std::error::Error + Send + Sync>),
+    /// The portal does not provide the setting.
+    NotFound {
+        /// Namespace of the setting.
+        namespace: String,
+        /// Key of the setting.
+        key: String,
+    },
+    /// The setting has a value of an unexpected type.
+    UnexpectedValue {
+        /// Description of the expected type.
+        expected: &'static str,
+        /// The value found, `None` if its type is not supported by [`Value`].
+        found: Option<Value>,
+    },
+    /// The connection to the bus was lost, reported by subscriptions before
+    /// reconnecting.
+    Disconnected,
+    /// Any other D-Bus error, e.g., a timeout.
+    DBus(Box<dyn std::error::Error + Send + Sync>),
+    /// Setting up I/O failed, e.g., for the connection or for watching
+    /// configuration files.
+    Io(io::Error),
+    /// A configuration file read by a fallback, e.g.,
+    /// [`DconfBackend`](crate::DconfBackend), cannot be read.
+    Config {
+        /// Path of the file, relative if the configuration directory is
+        /// unknown.
+        path: PathBuf,
+        /// The error reading it.
+        source: io::Error,
+    },
+    /// A custom [`Backend`](crate::Backend) failed.
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+    /// A custom [`Backend`](crate::Backend) is not available, e.g., because
+    /// the desktop it reads is not used, so a [`Detector`](crate::Detector)
+    /// tries the next one.
+    BackendUnavailable(Box<dyn std::error::Error + Send + Sync>),
+    /// The [`Detector`](crate::Detector) has no backends.
+    NoBackend,
+}
+
+// Errors need to be able to cross thread boundaries, e.g., from a subscription.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Error>();
+};
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoSessionBus(_) => f.write_str("cannot connect to the bus"),
+            Error::PortalUnavailable(_) => f.write_str("the settings portal is not available"),
+            Error::NotFound { namespace, key } => {
+                write!(f, "setting `{key}` not found in `{namespace}`")
+            }
+            Error::UnexpectedValue {
+                expected,
+                found: Some(found),
+            } => write!(f, "expected {expected}, found `{found}`"),
+            Error::UnexpectedValue {
+                expected,
+                found: None,
+            } => write!(f, "expected {expected}, found unsupported type"),
+            Error::Disconnected => f.write_str("the connection to the bus was lost"),
+            Error::DBus(_) => f.write_str("D-Bus call failed"),
+            Error::Io(_) => f.write_str("I/O error"),
+            Error::Config { path, .. } => {
+                write!(f, "cannot read configuration file `{}`", path.display())
+            }
+            Error::Backend(_) => f.write_str("backend failed"),
+            Error::BackendUnavailable(_) => f.write_str("backend is not available"),
+            Error::NoBackend => f.write_str("no backend to detect the mode"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::NoSessionBus(source)
+            | Error::PortalUnavailable(source)
+            | Error::DBus(source)
+            | Error::Backend(source)
+            | Error::BackendUnavailable(source) => Some(&**source),
+            Error::Io(source) | Error::Config { source, .. } => Some(source),
+            Error::NotFound { .. }
+            | Error::UnexpectedValue { .. }
+            | Error::Disconnected
+            | Error::NoBackend => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl Error {
+    /// Returns whether the source of the mode is not available, e.g., because
+    /// there is no session bus or portal, the portal does not provide the
+    /// setting, or a configuration file does not exist.
+    ///
+    /// When all of its backends fail, a [`Detector`](crate::Detector) returns
+    /// the first error that is not such an error.
+    #[must_use]
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            Error::NoSessionBus(_)
+            | Error::PortalUnavailable(_)
+            | Error::NotFound { .. }
+            | Error::BackendUnavailable(_) => true,
+            Error::Config { source, .. } => source.kind() == io::ErrorKind::NotFound,
+            _ => false,
+        }
+    }
+
+    /// Returns [`Error::Config`] for `source` reading `path`.
+    pub(crate) fn config(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Self::Config {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// Classifies a D-Bus error by its `name`.
+    pub(crate) fn dbus(
+        name: Option<&str>,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        let source = Box::new(source);
+        match name {
+            Some(
+                "org.freedesktop.DBus.Error.ServiceUnknown"
+                | "org.freedesktop.DBus.Error.NameHasNoOwner"
+                | "org.freedesktop.DBus.Error.UnknownObject"
+                | "org.freedesktop.DBus.Error.UnknownInterface"
+                | UNKNOWN_METHOD,
+            ) => Self::PortalUnavailable(source),
+            // Activating the portal failed.
+            Some(name) if name.starts_with("org.freedesktop.DBus.Error.Spawn.") => {
+                Self::PortalUnavailable(source)
+            }
+            _ => Self::DBus(source),
+        }
+    }
+
+    /// Returns [`Error::NotFound`] for a D-Bus error with `name` returned when
+    /// reading `key` in `namespace`, otherwise classifies it like
+    /// [`Error::dbus`].
+    pub(crate) fn read(
+        name: Option<&str>,
+        source: impl std::error::Error + Send + Sync + 'static,
+        namespace: &str,
+        key: &str,
+    ) -> Self {
+        if name == Some("org.freedesktop.portal.Error.NotFound") {
+            Self::NotFound {
+                namespace: namespace.to_owned(),
+                key: key.to_owned(),
+            }
+        } else {
+            Self::dbus(name, source)
+        }
+    }
+}
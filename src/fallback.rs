@@ -0,0 +1,145 @@
+//! Backends reading the desktop's configuration files, used when the portal is
+//! not available.
+
+use std::path::PathBuf;
+use std::{fs, io, thread};
+
+use crate::{Error, Mode, Subscription};
+
+mod notify;
+
+use notify::Changes;
+
+/// Reads the [`Mode`] from configuration files.
+pub(crate) struct Fallback {
+    /// Returns the files read, in order of precedence.
+    pub(crate) paths: fn() -> Result<Vec<PathBuf>, Error>,
+    /// Reads the mode from the files, errors when none of them can be read.
+    pub(crate) read: fn(&[PathBuf]) -> Result<Mode, Error>,
+}
+
+impl Fallback {
+    pub(crate) fn detect(&self) -> Result<Mode, Error> {
+        (self.read)(&(self.paths)()?)
+    }
+
+    /// Calls `call_back` with the current [`Mode`] and every time it changes,
+    /// watching the files for changes.
+    pub(crate) fn watch(
+        &self,
+        mut call_back: impl FnMut(Mode) + Send + 'static,
+    ) -> Result<Subscription, Error> {
+        let read = self.read;
+        // Watching before reading, so no change is missed.
+        let (mut changes, stop) = Changes::new((self.paths)()?)?;
+        let mut current = read(changes.paths())?;
+        call_back(current);
+
+        let thread = thread::spawn(move || {
+            while changes.wait()? {
+                // Files can be missing while they are replaced.
+                if let Ok(mode) = read(changes.paths()) {
+                    if mode != current {
+                        current = mode;
+                        call_back(mode);
+                    }
+                }
+            }
+            Ok(())
+        });
+        Ok(Subscription::from_stop(move || {
+            stop.stop();
+            thread
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        }))
+    }
+}
+
+/// Returns `$XDG_CONFIG_HOME`, defaulting to `$HOME/.config`, errors with
+/// [`Error::Config`] for `file` if neither is set.
+pub(crate) fn config_home(file: &str) -> Result<PathBuf, Error> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .filter(|home| !home.is_empty())
+                .map(|home| PathBuf::from(home).join(".config"))
+        })
+        .ok_or_else(|| {
+            let source = io::Error::new(
+                io::ErrorKind::NotFound,
+                "neither XDG_CONFIG_HOME nor HOME is set",
+            );
+            Error::config(file, source)
+        })
+}
+
+/// Returns `$XDG_CONFIG_DIRS`, defaulting to `/etc/xdg`.
+pub(crate) fn config_dirs() -> Vec<PathBuf> {
+    let dirs: Vec<PathBuf> = std::env::var_os("XDG_CONFIG_DIRS")
+        .map(|dirs| {
+            std::env::split_paths(&dirs)
+                .filter(|path| path.is_absolute())
+                .collect()
+        })
+        .unwrap_or_default();
+    if dirs.is_empty() {
+        vec![PathBuf::from("/etc/xdg")]
+    } else {
+        dirs
+    }
+}
+
+/// Returns the `files` relative to [`config_home`] and then to each of the
+/// [`config_dirs`], in order of precedence.
+pub(crate) fn config_paths(files: &[&str]) -> Result<Vec<PathBuf>, Error> {
+    let mut dirs = vec![config_home(files[0])?];
+    dirs.extend(config_dirs());
+    Ok(dirs
+        .iter()
+        .flat_map(|dir| files.iter().map(move |file| dir.join(file)))
+        .collect())
+}
+
+/// Reads the files at `paths` that exist, errors when there are none.
+pub(crate) fn read_files(paths: &[PathBuf]) -> Result<Vec<String>, Error> {
+    let mut files = Vec::new();
+    for path in paths {
+        match fs::read_to_string(path) {
+            Ok(contents) => files.push(contents),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(Error::config(path, error)),
+        }
+    }
+    if files.is_empty() {
+        let source = io::Error::new(
+            io::ErrorKind::NotFound,
+            "not found in any configuration directory",
+        );
+        return Err(Error::config(&paths[0], source));
+    }
+    Ok(files)
+}
+
+/// Returns the value of `key` in `group` of an INI-style `contents`, e.g.,
+/// GTK's `settings.ini` or KDE's configuration files.
+pub(crate) fn ini_value<'a>(contents: &'a str, group: &str, key: &str) -> Option<&'a str> {
+    let mut in_group = false;
+    let mut value = None;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            in_group = name.strip_suffix(']') == Some(group);
+        } else if let Some((name, found)) = line.split_once('=') {
+            // Later entries override earlier ones.
+            if in_group && name.trim_end() == key {
+                value = Some(found.trim_start());
+            }
+        }
+    }
+    value
+}
@@ -0,0 +1,205 @@
+//! Notification of changes to configuration files, through inotify on Linux
+//! and by checking them periodically elsewhere.
+
+#[cfg(target_os = "linux")]
+pub(crate) use inotify::Changes;
+#[cfg(not(target_os = "linux"))]
+pub(crate) use poll::Changes;
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::io::{self, Read, Write};
+    use std::os::fd::{AsRawFd, FromRawFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::UnixStream;
+    use std::path::PathBuf;
+
+    use crate::Error;
+
+    /// Events in a directory that change the files in it, e.g., replacing
+    /// them by renaming, or the directory itself.
+    const EVENTS: u32 = libc::IN_CLOSE_WRITE
+        | libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO
+        | libc::IN_DELETE_SELF
+        | libc::IN_MOVE_SELF;
+
+    /// Waits for changes to files, by watching their directories.
+    pub(crate) struct Changes {
+        paths: Vec<PathBuf>,
+        inotify: File,
+        wake_up: UnixStream,
+    }
+
+    /// Handle to stop [`Changes::wait`] from another thread.
+    pub(crate) struct Stop(UnixStream);
+
+    impl Changes {
+        pub(crate) fn new(paths: Vec<PathBuf>) -> Result<(Self, Stop), Error> {
+            // SAFETY: Takes no pointers.
+            let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            // SAFETY: `fd` was just opened and is not owned by anything else.
+            let inotify = unsafe { File::from_raw_fd(fd) };
+            let (wake_up, stop) = UnixStream::pair()?;
+            let changes = Self {
+                paths,
+                inotify,
+                wake_up,
+            };
+            changes.watch();
+            Ok((changes, Stop(stop)))
+        }
+
+        pub(crate) fn paths(&self) -> &[PathBuf] {
+            &self.paths
+        }
+
+        /// Watches the closest existing directory containing each file, so
+        /// files and directories created later are noticed as well.
+        fn watch(&self) {
+            for path in &self.paths {
+                for dir in path.ancestors().skip(1) {
+                    let Ok(dir) = CString::new(dir.as_os_str().as_bytes()) else {
+                        break;
+                    };
+                    // SAFETY: `dir` is a nul-terminated string. Watching a
+                    // directory again only updates its watch.
+                    if unsafe {
+                        libc::inotify_add_watch(self.inotify.as_raw_fd(), dir.as_ptr(), EVENTS)
+                    } >= 0
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+
+        /// Waits until any of the files might have changed, returns `false`
+        /// when stopped.
+        pub(crate) fn wait(&mut self) -> Result<bool, Error> {
+            let mut fds = [
+                libc::pollfd {
+                    fd: self.inotify.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: self.wake_up.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+            ];
+            loop {
+                // SAFETY: `fds` is an array of two valid `pollfd`s.
+                if unsafe { libc::poll(fds.as_mut_ptr(), 2, -1) } >= 0 {
+                    break;
+                }
+                let error = io::Error::last_os_error();
+                if error.kind() != io::ErrorKind::Interrupted {
+                    return Err(error.into());
+                }
+            }
+            if fds[1].revents != 0 {
+                return Ok(false);
+            }
+            // Only whether there were events matters.
+            let mut events = [0; 4096];
+            loop {
+                match self.inotify.read(&mut events) {
+                    Ok(_) => {}
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                    Err(error) => return Err(error.into()),
+                }
+            }
+            // Directories could have been created.
+            self.watch();
+            Ok(true)
+        }
+    }
+
+    impl Stop {
+        pub(crate) fn stop(&self) {
+            let _ = (&self.0).write(&[0]);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod poll {
+    use std::fs::{self, Metadata};
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    use crate::portal::Stopper;
+    use crate::Error;
+
+    /// Interval between checks whether the files changed.
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Waits for changes to files, by checking their metadata periodically.
+    pub(crate) struct Changes {
+        paths: Vec<PathBuf>,
+        last: Vec<Option<(Option<SystemTime>, u64)>>,
+        stopper: Stopper,
+    }
+
+    /// Handle to stop [`Changes::wait`] from another thread.
+    pub(crate) struct Stop(Stopper);
+
+    impl Changes {
+        pub(crate) fn new(paths: Vec<PathBuf>) -> Result<(Self, Stop), Error> {
+            let stopper = Stopper::new()?;
+            let changes = Self {
+                last: stamps(&paths),
+                paths,
+                stopper: stopper.clone(),
+            };
+            Ok((changes, Stop(stopper)))
+        }
+
+        pub(crate) fn paths(&self) -> &[PathBuf] {
+            &self.paths
+        }
+
+        /// Waits until any of the files might have changed, returns `false`
+        /// when stopped.
+        #[allow(clippy::unnecessary_wraps)]
+        pub(crate) fn wait(&mut self) -> Result<bool, Error> {
+            while !self.stopper.wait(POLL_INTERVAL) {
+                let stamps = stamps(&self.paths);
+                if stamps != self.last {
+                    self.last = stamps;
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+    }
+
+    impl Stop {
+        pub(crate) fn stop(&self) {
+            self.0.stop();
+        }
+    }
+
+    /// Identifies the versions of `paths`, as files are usually replaced on
+    /// every write.
+    fn stamps(paths: &[PathBuf]) -> Vec<Option<(Option<SystemTime>, u64)>> {
+        paths
+            .iter()
+            .map(|path| fs::metadata(path).ok().as_ref().map(stamp))
+            .collect()
+    }
+
+    fn stamp(metadata: &Metadata) -> (Option<SystemTime>, u64) {
+        (metadata.modified().ok(), metadata.len())
+    }
+}
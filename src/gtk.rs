@@ -0,0 +1,67 @@
+//! Fallback reading the theme from GTK's `settings.ini`.
+
+use std::path::PathBuf;
+
+use crate::fallback::{self, ini_value, Fallback};
+use crate::{Backend, Error, Mode, Subscription, Value};
+
+const GROUP: &str = "Settings";
+
+/// [`Backend`] reading GTK's `settings.ini`, dark if
+/// `gtk-application-prefer-dark-theme` is set or `gtk-theme-name` ends in
+/// `-dark`, watching it for changes.
+///
+/// Otherwise the mode is [`Mode::Default`], as a light theme is usually just
+/// GTK's default rather than a preference.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GtkBackend;
+
+impl Backend for GtkBackend {
+    fn name(&self) -> &'static str {
+        "gtk"
+    }
+
+    fn detect(&self) -> Result<Mode, Error> {
+        FALLBACK.detect()
+    }
+
+    fn watch(&self, call_back: Box<dyn FnMut(Mode) + Send>) -> Result<Subscription, Error> {
+        FALLBACK.watch(call_back)
+    }
+}
+
+/// Reads `gtk-4.0/settings.ini` and `gtk-3.0/settings.ini` in
+/// `$XDG_CONFIG_HOME`, and then in `$XDG_CONFIG_DIRS`, where the first file
+/// containing a key takes precedence.
+const FALLBACK: Fallback = Fallback { paths, read };
+
+fn paths() -> Result<Vec<PathBuf>, Error> {
+    fallback::config_paths(&["gtk-4.0/settings.ini", "gtk-3.0/settings.ini"])
+}
+
+fn read(paths: &[PathBuf]) -> Result<Mode, Error> {
+    let files = fallback::read_files(paths)?;
+    let value = |key| {
+        files
+            .iter()
+            .find_map(|contents| ini_value(contents, GROUP, key))
+    };
+
+    let prefer_dark = value("gtk-application-prefer-dark-theme")
+        .map(|value| match value {
+            "1" | "true" => Ok(true),
+            "0" | "false" => Ok(false),
+            _ => Err(Error::UnexpectedValue {
+                expected: "boolean",
+                found: Some(Value::String(value.to_owned())),
+            }),
+        })
+        .transpose()?;
+    let theme = value("gtk-theme-name");
+    let dark_theme = theme.is_some_and(|theme| theme.to_ascii_lowercase().ends_with("-dark"));
+    Ok(if prefer_dark == Some(true) || dark_theme {
+        Mode::Dark
+    } else {
+        Mode::Default
+    })
+}
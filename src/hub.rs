@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+
+use crate::{Error, Mode, Subscription, SubscriptionBuilder};
+
+type CallBack = Arc<Mutex<dyn FnMut(Mode) + Send>>;
+
+/// One subscription shared by any number of [`Subscriber`]s.
+///
+/// Unlike [`subscribe`](crate::subscribe), which uses a background thread
+/// per call, and with `libdbus` a connection as well, a hub receives all
+/// changes on a single one and forwards them to its subscribers, which can be
+/// added and removed at any time:
+///
+/// ```no_run
+/// use darkmode::Hub;
+///
+/// let hub = Hub::new()?;
+/// let window = hub.subscribe(|mode| println!("window: {mode:?}"));
+/// let plugin = hub.subscribe(|mode| println!("plugin: {mode:?}"));
+/// drop(window);
+/// # Ok::<_, darkmode::Error>(())
+/// ```
+///
+/// Dropping the hub stops the subscription, its subscribers receive no
+/// further changes.
+pub struct Hub {
+    // Dropped first, joining the background thread.
+    subscription: Subscription,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+}
+
+struct State {
+    mode: Mode,
+    next_id: u64,
+    subscribers: BTreeMap<u64, CallBack>,
+}
+
+impl Hub {
+    /// Subscribes using the [`global`](crate::Client::global) client, see
+    /// [`SubscriptionBuilder::hub`] to configure the subscription.
+    ///
+    /// Watches only the portal, unless [overridden](crate::detect).
+    ///
+    /// # Errors
+    ///
+    /// Errors when the session bus or the portal cannot be reached.
+    pub fn new() -> Result<Self, Error> {
+        Subscription::builder().hub()
+    }
+
+    pub(crate) fn with_builder(builder: SubscriptionBuilder) -> Result<Self, Error> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                mode: Mode::default(),
+                next_id: 0,
+                subscribers: BTreeMap::new(),
+            }),
+        });
+        let subscription = {
+            let shared = Arc::downgrade(&shared);
+            builder.subscribe(move |mode| {
+                if let Some(shared) = shared.upgrade() {
+                    shared.dispatch(mode);
+                }
+            })?
+        };
+        Ok(Self {
+            subscription,
+            shared,
+        })
+    }
+
+    /// Calls `call_back` with the current [`Mode`] and every time it changes,
+    /// until the returned [`Subscriber`] is dropped.
+    ///
+    /// Callbacks are called on the hub's background thread, one after the
+    /// other, so they should return quickly.
+    pub fn subscribe(&self, call_back: impl FnMut(Mode) + Send + 'static) -> Subscriber {
+        let call_back: CallBack = Arc::new(Mutex::new(call_back));
+        // Hold the callback while registering it, so changes dispatched in
+        // the meantime are delivered after the current mode.
+        let mut locked = lock(&call_back);
+        let (id, mode) = {
+            let mut state = self.shared.state();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.subscribers.insert(id, call_back.clone());
+            (id, state.mode)
+        };
+        (*locked)(mode);
+        drop(locked);
+        Subscriber {
+            shared: Arc::downgrade(&self.shared),
+            id,
+        }
+    }
+
+    /// Returns the last [`Mode`] received.
+    #[must_use]
+    pub fn mode(&self) -> Mode {
+        self.shared.state().mode
+    }
+
+    /// Returns the number of [`Subscriber`]s.
+    #[must_use]
+    pub fn subscribers(&self) -> usize {
+        self.shared.state().subscribers.len()
+    }
+
+    /// Stops the subscription, see [`Subscription::unsubscribe`].
+    ///
+    /// # Errors
+    ///
+    /// Errors when the match rules could not be removed from the bus.
+    pub fn close(self) -> Result<(), Error> {
+        self.subscription.unsubscribe()
+    }
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn dispatch(&self, mode: Mode) {
+        // Not holding the state while calling, so callbacks can add and
+        // remove subscribers.
+        let subscribers: Vec<CallBack> = {
+            let mut state = self.state();
+            state.mode = mode;
+            state.subscribers.values().cloned().collect()
+        };
+        for call_back in subscribers {
+            (*lock(&call_back))(mode);
+        }
+    }
+}
+
+fn lock(call_back: &CallBack) -> MutexGuard<'_, dyn FnMut(Mode) + Send + 'static> {
+    call_back.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Handle to a subscriber of a [`Hub`], created by [`Hub::subscribe`].
+///
+/// Dropping it removes the subscriber. A change that is being delivered
+/// concurrently can still reach it.
+#[must_use = "dropping a `Subscriber` unsubscribes immediately"]
+pub struct Subscriber {
+    shared: Weak<Shared>,
+    id: u64,
+}
+
+impl Subscriber {
+    /// Removes the subscriber, same as dropping it.
+    pub fn unsubscribe(self) {}
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.upgrade() {
+            let call_back = shared.state().subscribers.remove(&self.id);
+            // Dropped outside the lock, as dropping the callback could drop
+            // other subscribers.
+            drop(call_back);
+        }
+    }
+}
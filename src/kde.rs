@@ -0,0 +1,106 @@
+//! Fallback reading the color scheme from KDE's `kdeglobals`.
+
+use std::path::PathBuf;
+
+use crate::fallback::{self, ini_value, Fallback};
+use crate::{Backend, Error, Mode, Subscription};
+
+/// [`Backend`] reading KDE's color scheme from `kdeglobals`, dark if its window
+/// background is, watching it for changes.
+///
+/// Without a window background that can be parsed, only a color scheme named
+/// dark counts, other names give [`Mode::Default`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KdeBackend;
+
+impl Backend for KdeBackend {
+    fn name(&self) -> &'static str {
+        "kde"
+    }
+
+    fn detect(&self) -> Result<Mode, Error> {
+        FALLBACK.detect()
+    }
+
+    fn watch(&self, call_back: Box<dyn FnMut(Mode) + Send>) -> Result<Subscription, Error> {
+        FALLBACK.watch(call_back)
+    }
+}
+
+/// Reads `kdeglobals` in `$XDG_CONFIG_HOME`, and then in `$XDG_CONFIG_DIRS`,
+/// where the first file containing a key takes precedence.
+const FALLBACK: Fallback = Fallback { paths, read };
+
+fn paths() -> Result<Vec<PathBuf>, Error> {
+    fallback::config_paths(&["kdeglobals"])
+}
+
+fn read(paths: &[PathBuf]) -> Result<Mode, Error> {
+    let files = fallback::read_files(paths)?;
+    let value = |group, key| {
+        files
+            .iter()
+            .find_map(|contents| ini_value(contents, group, key))
+    };
+
+    // The background decides, as a scheme's name need not tell whether it is
+    // dark. A color that cannot be parsed is ignored like a missing one.
+    if let Some([r, g, b]) = value("Colors:Window", "BackgroundNormal").and_then(rgb) {
+        return Ok(if is_dark(luminance(r, g, b)) {
+            Mode::Dark
+        } else {
+            Mode::Light
+        });
+    }
+    Ok(match value("General", "ColorScheme") {
+        Some(scheme) if scheme.to_ascii_lowercase().contains("dark") => Mode::Dark,
+        _ => Mode::Default,
+    })
+}
+
+/// Parses a color as KDE writes it, `r,g,b`, `r,g,b,a`, `#rrggbb` or
+/// `#aarrggbb`.
+fn rgb(color: &str) -> Option<[u8; 3]> {
+    let color = color.trim();
+    if let Some(hex) = color.strip_prefix('#') {
+        if !hex.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+            return None;
+        }
+        // The alpha channel comes first.
+        let hex = match hex.len() {
+            6 => hex,
+            8 => &hex[2..],
+            _ => return None,
+        };
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some([channel(0)?, channel(2)?, channel(4)?]);
+    }
+    let channels = color
+        .split(',')
+        .map(|channel| channel.trim().parse::<u8>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match *channels {
+        [r, g, b] | [r, g, b, _] => Some([r, g, b]),
+        _ => None,
+    }
+}
+
+/// Returns the relative luminance of a color.
+fn luminance(r: u8, g: u8, b: u8) -> f64 {
+    let linear = |channel: u8| {
+        let channel = f64::from(channel) / 255.;
+        if channel <= 0.040_45 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// Returns whether white text contrasts more with a background of `luminance`
+/// than black text.
+fn is_dark(luminance: f64) -> bool {
+    // (1 + 0.05) / (l + 0.05) > (l + 0.05) / (0 + 0.05)
+    (luminance + 0.05).powi(2) < 1.05 * 0.05
+}
@@ -7,150 +7,106 @@
 //!
 //! It is intended as a minimal crate to be used on top of `winit`'s built-in
 //! dark mode detection on other OSes.
-
-use std::fmt::Display;
-use std::thread;
-use std::time::Duration;
-
-use dbus::arg::{ReadAll, RefArg, Variant};
-use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
-use dbus::blocking::{Connection, Proxy};
-use dbus::message::SignalArgs;
-use dbus::Message;
-
+//!
+//! By default, D-Bus is accessed through `libdbus`, enabling the `zbus`
+//! feature switches to a pure-Rust implementation instead. As features are
+//! unified, this also happens when any other crate in the dependency graph
+//! enables it, which removes `ClientBuilder::build_with_dbus`. For the same
+//! reason, that method is not shown on docs.rs, which builds with all
+//! features.
+//!
+//! When no portal is available, or it reports no preference, [`detect`] and
+//! [`subscribe`] fall back to reading GNOME's `color-scheme` from the user's
+//! dconf database, KDE's color scheme from `kdeglobals`, or GTK's
+//! `settings.ini`. Setting `DARKMODE` to `dark`, `light` or `default`, or
+//! `GTK_THEME` to a theme ending in `:dark`, overrides all of them.
+//!
+//! To test code depending on it without a desktop session, the `mock` feature
+//! provides a stand-in portal running on a private `dbus-daemon`.
+
+#[cfg(not(any(feature = "dbus", feature = "zbus")))]
+compile_error!("either the `dbus` or the `zbus` feature needs to be enabled");
+
+mod appearance;
+mod backend;
+mod client;
+mod dconf;
+mod env;
+mod error;
+mod fallback;
+mod gtk;
+mod hub;
+mod kde;
+#[cfg(all(feature = "mock", unix))]
+pub mod mock;
+mod portal;
+mod settings;
+#[cfg(all(feature = "stream", unix))]
+mod stream;
+mod subscription;
+mod value;
+
+pub use appearance::{
+    accent_color, appearance, detect_contrast, detect_motion_preference, subscribe_accent_color,
+    subscribe_appearance, subscribe_contrast, subscribe_motion_preference, AccentColor, Appearance,
+    Contrast, MotionPreference,
+};
+pub use backend::{Backend, Detection, Detector, PortalBackend};
+pub use client::{Client, ClientBuilder};
+pub use dconf::DconfBackend;
+pub use error::Error;
+pub use gtk::GtkBackend;
+pub use hub::{Hub, Subscriber};
+pub use kde::KdeBackend;
+pub use portal::Namespaces;
+pub use settings::Settings;
+#[cfg(all(feature = "stream", unix))]
+pub use stream::{stream, ModeStream};
+pub use subscription::{subscribe, subscribe_events, Event, Subscription, SubscriptionBuilder};
+pub use value::Value;
+
+/// The color scheme preferred by the user.
 #[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
 #[repr(u32)]
 pub enum Mode {
+    /// No preference.
     #[default]
     Default,
+    /// Prefers a dark appearance.
     Dark,
+    /// Prefers a light appearance.
     Light,
 }
 
-fn mode_from_u32(mode: u32) -> Mode {
-    match mode {
-        1 => Mode::Dark,
-        2 => Mode::Light,
-        _ => Mode::Default,
-    }
-}
-
-const INTERFACE: &str = "org.freedesktop.portal.Settings";
-const NAMESPACE: &str = "org.freedesktop.appearance";
-const COLOR_SCHEME: &str = "color-scheme";
-
-#[derive(Debug)]
-pub struct Error(Box<dyn std::error::Error>);
-
-impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.fmt(f)
-    }
-}
-
-impl std::error::Error for Error {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        self.0.source()
-    }
-}
-
-impl Error {
-    pub fn new(error: impl std::error::Error + 'static) -> Self {
-        Self(Box::new(error))
-    }
-}
-
-impl From<dbus::Error> for Error {
-    fn from(value: dbus::Error) -> Self {
-        Self::new(value)
-    }
-}
-
-fn proxy() -> Result<Proxy<'static, Box<Connection>>, Error> {
-    let connection = Connection::new_session()?;
-    Ok(Proxy::new(
-        "org.freedesktop.portal.Desktop",
-        "/org/freedesktop/portal/desktop",
-        Duration::from_millis(100),
-        Box::new(connection),
-    ))
-}
-
+/// Detects the current [`Mode`].
+///
+/// When the portal is not available, e.g., because there is no session bus,
+/// or it reports no preference, the mode is read from the desktop's
+/// configuration instead:
+/// 1. GNOME's `color-scheme` setting in the user's dconf database.
+/// 2. KDE's color scheme in `kdeglobals`, dark if its window background is.
+/// 3. GTK's `settings.ini`, dark if `gtk-application-prefer-dark-theme` is set
+///    or `gtk-theme-name` ends in `-dark`, without a preference otherwise.
+///
+/// The first one with a preference is used, see [`Detector`] to configure
+/// them.
+///
+/// All of these are overridden by the environment, e.g., for debugging or
+/// testing, in this order of precedence:
+/// 1. `DARKMODE` set to `dark`, `light` or `default`.
+/// 2. `GTK_THEME` ending in `:dark`, as GTK applications use a dark theme then.
+///
+/// Subscriptions, streams and hubs only deliver the overridden mode as well,
+/// unless they use a [`Client`] explicitly. Except for [`subscribe`], they
+/// watch only the portal, without the fallbacks.
+///
+/// # Errors
+///
+/// Errors when neither the portal nor any of the fallbacks can be read, see
+/// [`Detector::detect`] for which error is returned.
 pub fn detect() -> Result<Mode, Error> {
-    let proxy = proxy()?;
-    let color_scheme = proxy.method_call::<(Variant<u32>,), _, _, _>(
-        INTERFACE,
-        "ReadOne",
-        (NAMESPACE, COLOR_SCHEME),
-    );
-
-    match color_scheme {
-        Ok((Variant(color_scheme),)) => Ok(mode_from_u32(color_scheme)),
-        _ if proxy.get::<u32>("org.freedesktop.portal.Settings", "version")? < 2 => {
-            Ok(mode_from_u32(
-                proxy
-                    .method_call::<(Variant<Variant<u32>>,), _, _, _>(
-                        INTERFACE,
-                        "Read",
-                        (NAMESPACE, COLOR_SCHEME),
-                    )?
-                    .0
-                    .0
-                    .0,
-            ))
-        }
-        Err(e) => Err(e.into()),
-    }
-}
-
-#[derive(Debug)]
-struct SettingChanged {
-    namespace: String,
-    key: String,
-    value: Variant<Box<dyn RefArg>>,
-}
-
-impl SignalArgs for SettingChanged {
-    const INTERFACE: &'static str = INTERFACE;
-    const NAME: &'static str = "SettingChanged";
-}
-
-impl ReadAll for SettingChanged {
-    fn read(i: &mut dbus::arg::Iter) -> Result<Self, dbus::arg::TypeMismatchError> {
-        Ok(Self {
-            namespace: i.read()?,
-            key: i.read()?,
-            value: i.read()?,
-        })
+    if let Some(mode) = env::mode() {
+        return Ok(mode);
     }
-}
-
-pub fn subscribe(mut call_back: impl FnMut(Mode) + Send + 'static) -> Result<(), Error> {
-    call_back(detect()?);
-    let proxy = proxy()?;
-
-    let token = proxy.match_signal(
-        move |ref dbg @ SettingChanged {
-                  ref namespace,
-                  ref key,
-                  ref value,
-              },
-              _: &Connection,
-              _: &Message| {
-            if namespace == NAMESPACE && key == COLOR_SCHEME {
-                if let Some(value) = value.0.as_u64() {
-                    call_back(mode_from_u32(value.try_into().unwrap_or_default()));
-                }
-            }
-            true
-        },
-    )?;
-    thread::spawn(move || {
-        let _ = token;
-
-        loop {
-            _ = proxy.connection.process(Duration::from_secs(1));
-        }
-    });
-    Ok(())
+    Detector::default().detect().map(|detection| detection.mode)
 }
@@ -0,0 +1,385 @@
+//! Stand-in for the settings portal, to test code using this crate without a
+//! desktop session.
+//!
+//! [`MockPortal`] starts a private `dbus-daemon`, which needs to be installed,
+//! and serves `org.freedesktop.portal.Settings` on it. Connect to it using
+//! [`MockPortal::client`]:
+//!
+//! ```no_run
+//! use darkmode::mock::MockPortal;
+//! use darkmode::Mode;
+//!
+//! let portal = MockPortal::start()?;
+//! portal.set("org.freedesktop.appearance", "color-scheme", Mode::Dark)?;
+//! assert_eq!(portal.client()?.detect()?, Mode::Dark);
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::{env, fs};
+
+use zbus::blocking::connection::Builder;
+use zbus::message::{Header, Message};
+use zbus::names::{BusName, ErrorName};
+use zbus::zvariant::{self, OwnedValue, StructureBuilder};
+use zbus::{interface, DBusError};
+
+use crate::portal::{DESTINATION, INTERFACE, PATH, SETTING_CHANGED};
+use crate::{Client, Error, Value};
+
+const CONFIG: &str = r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-BUS Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:path={path}</listen>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#;
+
+/// Settings portal on a private bus, stopped on drop.
+///
+/// It starts out implementing version 2 of the portal without any settings.
+///
+/// Values are sent with the smallest of the D-Bus types the crate's [`Value`]
+/// represents, i.e., integers as `u`/`i` when they fit into 32 bits,
+/// [arrays](Value::Array) as `av` and [dicts](Value::Dict) as `a{sv}`, with
+/// keys converted to strings.
+pub struct MockPortal {
+    connection: zbus::blocking::Connection,
+    state: Arc<Mutex<State>>,
+    // Dropped last to let the connection close cleanly.
+    daemon: Daemon,
+}
+
+impl MockPortal {
+    /// Starts a `dbus-daemon` and the portal on it.
+    ///
+    /// # Errors
+    ///
+    /// Errors when `dbus-daemon` cannot be started or the portal cannot be
+    /// served.
+    pub fn start() -> io::Result<Self> {
+        let daemon = Daemon::start()?;
+        let state = Arc::new(Mutex::new(State {
+            version: 2,
+            settings: BTreeMap::new(),
+        }));
+        let connection = serve(&daemon.address, &state)?;
+        Ok(Self {
+            connection,
+            state,
+            daemon,
+        })
+    }
+
+    /// Returns the address of the bus.
+    #[must_use]
+    pub fn address(&self) -> &str {
+        &self.daemon.address
+    }
+
+    /// Returns a [`Client`] connected to the bus.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the bus cannot be reached.
+    pub fn client(&self) -> Result<Client, Error> {
+        Client::builder().address(self.address()).build()
+    }
+
+    /// Sets the portal's `version`, `ReadOne` is unknown below 2.
+    pub fn set_version(&self, version: u32) {
+        self.state().version = version;
+    }
+
+    /// Sets `key` in `namespace` to `value`, emitting `SettingChanged`.
+    ///
+    /// # Errors
+    ///
+    /// Errors when `value` cannot be represented in D-Bus, i.e., is an empty
+    /// [`Value::Struct`], or the signal cannot be sent.
+    pub fn set(&self, namespace: &str, key: &str, value: impl Into<Value>) -> io::Result<()> {
+        let value = value.into();
+        let variant = to_zvariant(&value).map_err(io::Error::other)?;
+        self.state()
+            .settings
+            .entry(namespace.to_owned())
+            .or_default()
+            .insert(key.to_owned(), value);
+        self.connection
+            .emit_signal(
+                None::<BusName<'_>>,
+                PATH,
+                INTERFACE,
+                SETTING_CHANGED,
+                &(namespace, key, variant),
+            )
+            .map_err(io::Error::other)
+    }
+
+    /// Removes `key` in `namespace`, without emitting a signal.
+    pub fn remove(&self, namespace: &str, key: &str) {
+        if let Some(settings) = self.state().settings.get_mut(namespace) {
+            settings.remove(key);
+        }
+    }
+
+    /// Releases or requests the portal's bus name, making the portal
+    /// unavailable or available again.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the bus rejects the request.
+    pub fn set_available(&self, available: bool) -> io::Result<()> {
+        if available {
+            self.connection.request_name(DESTINATION)
+        } else {
+            self.connection.release_name(DESTINATION).map(drop)
+        }
+        .map_err(io::Error::other)
+    }
+
+    /// Restarts the bus at the same address, disconnecting all clients, and
+    /// serves the portal with the same settings on the new one.
+    ///
+    /// # Errors
+    ///
+    /// Errors when `dbus-daemon` cannot be restarted or the portal cannot be
+    /// served.
+    pub fn restart_bus(&mut self) -> io::Result<()> {
+        self.daemon.restart()?;
+        self.connection = serve(&self.daemon.address, &self.state)?;
+        Ok(())
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn serve(address: &str, state: &Arc<Mutex<State>>) -> io::Result<zbus::blocking::Connection> {
+    Builder::address(address)
+        .and_then(|builder| builder.serve_at(PATH, Portal(state.clone())))
+        .and_then(|builder| builder.name(DESTINATION))
+        .and_then(Builder::build)
+        .map_err(io::Error::other)
+}
+
+/// Private `dbus-daemon` listening in a temporary directory.
+struct Daemon {
+    child: Child,
+    address: String,
+    dir: PathBuf,
+}
+
+impl Daemon {
+    fn start() -> io::Result<Self> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = env::temp_dir().join(format!(
+            "darkmode-mock-{}-{}",
+            process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir)?;
+        let config = dir.join("bus.conf");
+        let socket = dir.join("bus");
+        fs::write(&config, CONFIG.replace("{path}", &socket.to_string_lossy()))?;
+        let mut daemon = Self {
+            child: spawn(&config).inspect_err(|_| _ = fs::remove_dir_all(&dir))?,
+            // Without the bus' GUID, which changes on restart.
+            address: format!("unix:path={}", socket.display()),
+            dir,
+        };
+        wait_until_listening(&mut daemon.child)?;
+        Ok(daemon)
+    }
+
+    fn restart(&mut self) -> io::Result<()> {
+        _ = self.child.kill();
+        _ = self.child.wait();
+        _ = fs::remove_file(self.dir.join("bus"));
+        self.child = spawn(&self.dir.join("bus.conf"))?;
+        wait_until_listening(&mut self.child)
+    }
+}
+
+fn spawn(config: &Path) -> io::Result<Child> {
+    Command::new("dbus-daemon")
+        .arg("--nofork")
+        .arg("--print-address")
+        .arg(format!("--config-file={}", config.display()))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+}
+
+/// Waits until the daemon prints its address, i.e., accepts connections.
+fn wait_until_listening(child: &mut Child) -> io::Result<()> {
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let mut address = String::new();
+    BufReader::new(stdout).read_line(&mut address)?;
+    if address.trim_end().is_empty() {
+        return Err(io::Error::other("`dbus-daemon` did not print its address"));
+    }
+    Ok(())
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        _ = self.child.kill();
+        _ = self.child.wait();
+        _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+struct State {
+    version: u32,
+    settings: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+/// The `org.freedesktop.portal.Settings` interface.
+struct Portal(Arc<Mutex<State>>);
+
+impl Portal {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn get(&self, namespace: &str, key: &str) -> Result<zvariant::Value<'static>, PortalError> {
+        let state = self.state();
+        let value = state
+            .settings
+            .get(namespace)
+            .and_then(|settings| settings.get(key))
+            .ok_or(PortalError {
+                name: "org.freedesktop.portal.Error.NotFound",
+                description: "Requested setting not found".to_owned(),
+            })?;
+        Ok(checked(to_zvariant(value)))
+    }
+}
+
+#[interface(name = "org.freedesktop.portal.Settings")]
+impl Portal {
+    // Arguments are deserialized by `zbus`.
+    #[allow(clippy::needless_pass_by_value)]
+    fn read_all(&self, namespaces: Vec<String>) -> HashMap<String, HashMap<String, OwnedValue>> {
+        let matches = |namespace: &str| {
+            namespaces.is_empty()
+                || namespaces.iter().any(|pattern| {
+                    pattern
+                        .strip_suffix('*')
+                        .map_or(pattern == namespace, |prefix| namespace.starts_with(prefix))
+                })
+        };
+        self.state()
+            .settings
+            .iter()
+            .filter(|(namespace, _)| matches(namespace))
+            .map(|(namespace, settings)| {
+                let settings = settings
+                    .iter()
+                    .map(|(key, value)| {
+                        let value =
+                            checked(to_zvariant(value).and_then(|value| value.try_to_owned()));
+                        (key.clone(), value)
+                    })
+                    .collect();
+                (namespace.clone(), settings)
+            })
+            .collect()
+    }
+
+    fn read(&self, namespace: &str, key: &str) -> Result<OwnedValue, PortalError> {
+        // `Read` wraps the value in an additional variant.
+        let value = zvariant::Value::Value(Box::new(self.get(namespace, key)?));
+        Ok(checked(value.try_to_owned()))
+    }
+
+    fn read_one(&self, namespace: &str, key: &str) -> Result<OwnedValue, PortalError> {
+        if self.state().version < 2 {
+            return Err(PortalError {
+                name: "org.freedesktop.DBus.Error.UnknownMethod",
+                description: "Unknown method ReadOne".to_owned(),
+            });
+        }
+        Ok(checked(self.get(namespace, key)?.try_to_owned()))
+    }
+
+    #[zbus(property)]
+    fn version(&self) -> u32 {
+        self.state().version
+    }
+}
+
+#[derive(Debug)]
+struct PortalError {
+    name: &'static str,
+    description: String,
+}
+
+impl DBusError for PortalError {
+    fn create_reply(&self, header: &Header<'_>) -> zbus::Result<Message> {
+        Message::error(header, self.name())?.build(&(&self.description,))
+    }
+
+    fn name(&self) -> ErrorName<'_> {
+        ErrorName::from_static_str_unchecked(self.name)
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some(&self.description)
+    }
+}
+
+/// Unwraps the conversion of a value that was already converted successfully
+/// in [`MockPortal::set`].
+fn checked<T>(result: zvariant::Result<T>) -> T {
+    result.expect("values are checked in `MockPortal::set`")
+}
+
+fn to_zvariant(value: &Value) -> zvariant::Result<zvariant::Value<'static>> {
+    use zvariant::Value as V;
+    Ok(match value {
+        Value::Bool(value) => V::Bool(*value),
+        Value::I64(value) => i32::try_from(*value).map_or(V::I64(*value), V::I32),
+        Value::U64(value) => u32::try_from(*value).map_or(V::U64(*value), V::U32),
+        Value::F64(value) => V::F64(*value),
+        Value::String(value) => V::from(value.clone()),
+        Value::Array(values) => V::from(
+            values
+                .iter()
+                .map(to_zvariant)
+                .collect::<zvariant::Result<Vec<_>>>()?,
+        ),
+        Value::Dict(entries) => V::from(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        Value::String(key) => key.clone(),
+                        key => key.to_string(),
+                    };
+                    Ok((key, to_zvariant(value)?))
+                })
+                .collect::<zvariant::Result<HashMap<_, _>>>()?,
+        ),
+        Value::Struct(fields) => V::from(
+            fields
+                .iter()
+                .try_fold(StructureBuilder::new(), |builder, field| {
+                    to_zvariant(field).map(|field| builder.append_field(field))
+                })?
+                .build()?,
+        ),
+    })
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[cfg(not(feature = "zbus"))]
+mod libdbus;
+#[cfg(feature = "zbus")]
+mod zbus;
+
+#[cfg(all(feature = "stream", unix, not(feature = "zbus")))]
+pub(crate) use libdbus::SettingStream;
+#[cfg(not(feature = "zbus"))]
+pub(crate) use libdbus::{Connection, Listener, Stopper};
+
+#[cfg(all(feature = "stream", unix, feature = "zbus"))]
+pub(crate) use self::zbus::SettingStream;
+#[cfg(feature = "zbus")]
+pub(crate) use self::zbus::{Connection, Listener, Stopper};
+use crate::{Error, Mode, Value};
+
+pub(crate) const DESTINATION: &str = "org.freedesktop.portal.Desktop";
+pub(crate) const PATH: &str = "/org/freedesktop/portal/desktop";
+pub(crate) const TIMEOUT: Duration = Duration::from_millis(100);
+pub(crate) const INTERFACE: &str = "org.freedesktop.portal.Settings";
+pub(crate) const SETTING_CHANGED: &str = "SettingChanged";
+pub(crate) const NAMESPACE: &str = "org.freedesktop.appearance";
+const BUS: &str = "org.freedesktop.DBus";
+const NAME_OWNER_CHANGED: &str = "NameOwnerChanged";
+pub(crate) const UNKNOWN_METHOD: &str = "org.freedesktop.DBus.Error.UnknownMethod";
+
+/// How to connect to the portal.
+#[derive(Debug, Clone)]
+pub(crate) struct Config {
+    pub(crate) timeout: Duration,
+    /// Address of the bus, `None` for the session bus.
+    pub(crate) address: Option<String>,
+    pub(crate) destination: String,
+    pub(crate) path: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timeout: TIMEOUT,
+            address: None,
+            destination: DESTINATION.to_owned(),
+            path: PATH.to_owned(),
+        }
+    }
+}
+
+/// Settings to receive `SettingChanged` signals for, filtered by the bus on
+/// the signal's arguments to avoid waking up for other changes.
+#[derive(Debug, Clone)]
+pub(crate) struct Filter {
+    pub(crate) namespace: String,
+    /// `None` for all keys in the namespace.
+    pub(crate) key: Option<String>,
+}
+
+impl Filter {
+    pub(crate) fn namespace(namespace: &str) -> Self {
+        Self {
+            namespace: namespace.to_owned(),
+            key: None,
+        }
+    }
+
+    pub(crate) fn setting(namespace: &str, key: &str) -> Self {
+        Self {
+            namespace: namespace.to_owned(),
+            key: Some(key.to_owned()),
+        }
+    }
+
+    pub(crate) fn of<T: Setting>() -> Self {
+        Self::setting(NAMESPACE, T::KEY)
+    }
+}
+
+/// Settings by key, by namespace, as returned by `ReadAll`.
+///
+/// Values of unsupported types are omitted.
+pub type Namespaces = HashMap<String, HashMap<String, Value>>;
+
+/// A setting in the `org.freedesktop.appearance` namespace.
+pub(crate) trait Setting: Sized {
+    const KEY: &'static str;
+    /// Description of the expected D-Bus type, used in errors.
+    const EXPECTED: &'static str;
+
+    /// Returns `None` if the value has an unexpected type.
+    fn from_value(value: &Value) -> Option<Self>;
+
+    fn read(connection: &Connection) -> Result<Self, Error> {
+        Self::convert(connection.read(NAMESPACE, Self::KEY)?.as_ref())
+    }
+
+    /// Returns [`Error::UnexpectedValue`] if the value has an unexpected or
+    /// unsupported type.
+    fn convert(value: Option<&Value>) -> Result<Self, Error> {
+        value
+            .and_then(Self::from_value)
+            .ok_or_else(|| Error::UnexpectedValue {
+                expected: Self::EXPECTED,
+                found: value.cloned(),
+            })
+    }
+}
+
+impl Setting for Mode {
+    const EXPECTED: &'static str = "u32";
+    const KEY: &'static str = "color-scheme";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        Some(match value.as_u32()? {
+            1 => Mode::Dark,
+            2 => Mode::Light,
+            _ => Mode::Default,
+        })
+    }
+}
+
+/// Signal received by a `Listener`.
+#[derive(Debug)]
+pub(crate) enum Signal {
+    SettingChanged(SettingChanged),
+    /// The portal's bus name changed its owner, `false` if it has none now.
+    OwnerChanged(bool),
+}
+
+/// Arguments of the `SettingChanged` signal.
+#[derive(Debug)]
+pub(crate) struct SettingChanged {
+    namespace: String,
+    key: String,
+    value: Option<Value>,
+}
+
+impl SettingChanged {
+    /// Returns whether this change is for `key` in `namespace`.
+    pub(crate) fn is(&self, namespace: &str, key: &str) -> bool {
+        self.namespace == namespace && self.key == key
+    }
+
+    /// Returns the new value, `None` if its type is not supported.
+    pub(crate) fn value(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+
+    /// Returns the new value if this change is for `T`.
+    ///
+    /// Errors when the value has an unexpected type.
+    pub(crate) fn get<T: Setting>(&self) -> Option<Result<T, Error>> {
+        self.is(NAMESPACE, T::KEY).then(|| T::convert(self.value()))
+    }
+}
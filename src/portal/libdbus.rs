@@ -0,0 +1,495 @@
+//! Backend using `libdbus` through the `dbus` crate.
+
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::io::Write;
+#[cfg(unix)]
+use std::os::fd::AsRawFd;
+#[cfg(all(feature = "stream", unix))]
+use std::os::fd::{AsFd, BorrowedFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(all(feature = "stream", unix))]
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+#[cfg(all(feature = "stream", unix))]
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+#[cfg(all(feature = "stream", unix))]
+use async_io::Async;
+use dbus::arg::{ArgType, PropMap, RefArg, Variant};
+use dbus::blocking::Proxy;
+use dbus::channel::{default_reply, BusType, Channel};
+use dbus::message::{MatchRule, MessageType};
+use dbus::Message;
+#[cfg(all(feature = "stream", unix))]
+use futures_core::Stream;
+
+use super::{
+    Config, Filter, Namespaces, SettingChanged, Signal, Value, BUS, INTERFACE, NAME_OWNER_CHANGED,
+    SETTING_CHANGED, UNKNOWN_METHOD,
+};
+use crate::Error;
+
+/// Connection used for method calls, shared between threads.
+///
+/// Signals are received on separate channels instead, as popping messages
+/// from a shared channel could steal replies from concurrent method calls.
+#[derive(Clone)]
+pub(crate) struct Connection {
+    // `dbus::blocking::Connection` is not `Sync`.
+    connection: Arc<Mutex<dbus::blocking::Connection>>,
+    config: Arc<Config>,
+}
+
+impl Connection {
+    pub(crate) fn new(config: Config) -> Result<Self, Error> {
+        Ok(Self::with_connection(open(&config)?.into(), config))
+    }
+
+    pub(crate) fn with_connection(connection: dbus::blocking::Connection, config: Config) -> Self {
+        Self {
+            connection: Arc::new(Mutex::new(connection)),
+            config: Arc::new(config),
+        }
+    }
+
+    fn portal(&self) -> Proxy<'_, MutexGuard<'_, dbus::blocking::Connection>> {
+        Proxy::new(
+            &*self.config.destination,
+            &*self.config.path,
+            self.config.timeout,
+            self.connection
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+
+    pub(crate) fn read(&self, namespace: &str, key: &str) -> Result<Option<Value>, Error> {
+        let portal = self.portal();
+        match portal.method_call::<(Variant<Box<dyn RefArg>>,), _, _, _>(
+            INTERFACE,
+            "ReadOne",
+            (namespace, key),
+        ) {
+            Ok((value,)) => Ok(to_value(&value)),
+            // Version 1 only supports `Read`, which wraps the value in an additional variant.
+            Err(error) if error.name() == Some(UNKNOWN_METHOD) => {
+                let (value,) = portal
+                    .method_call::<(Variant<Box<dyn RefArg>>,), _, _, _>(
+                        INTERFACE,
+                        "Read",
+                        (namespace, key),
+                    )
+                    .map_err(|error| Error::read(name(&error).as_deref(), error, namespace, key))?;
+                Ok(to_value(&value))
+            }
+            Err(error) => Err(Error::read(name(&error).as_deref(), error, namespace, key)),
+        }
+    }
+
+    pub(crate) fn read_all(&self, namespaces: &[&str]) -> Result<Namespaces, Error> {
+        let (namespaces,) = self
+            .portal()
+            .method_call::<(HashMap<String, PropMap>,), _, _, _>(
+                INTERFACE,
+                "ReadAll",
+                (namespaces,),
+            )?;
+        Ok(namespaces
+            .into_iter()
+            .map(|(namespace, settings)| {
+                let settings = settings
+                    .into_iter()
+                    .filter_map(|(key, value)| Some((key, to_value(&value)?)))
+                    .collect();
+                (namespace, settings)
+            })
+            .collect())
+    }
+
+    pub(crate) fn listen(&self, filter: &Filter, stopper: &Stopper) -> Result<Listener, Error> {
+        let channel = open(&self.config)?;
+        let match_rules = vec![
+            add_match(
+                &channel,
+                &self.config,
+                setting_changed_rule(&self.config, filter),
+            )?,
+            add_match(
+                &channel,
+                &self.config,
+                name_owner_changed_rule(&self.config),
+            )?,
+        ];
+        Ok(Listener {
+            channel,
+            match_rules,
+            config: self.config.clone(),
+            stopper: stopper.clone(),
+        })
+    }
+
+    /// Opens a new connection with the same configuration, e.g., after this
+    /// one was disconnected.
+    pub(crate) fn reconnect(&self) -> Result<Self, Error> {
+        Self::new((*self.config).clone())
+    }
+
+    #[cfg(all(feature = "stream", unix))]
+    pub(crate) fn stream(&self, filter: &Filter) -> Result<SettingStream, Error> {
+        let channel = open(&self.config)?;
+        add_match(
+            &channel,
+            &self.config,
+            setting_changed_rule(&self.config, filter),
+        )?;
+        Ok(SettingStream {
+            watch: Async::new(WatchFd(channel.watch().fd))?,
+            channel,
+        })
+    }
+}
+
+fn open(config: &Config) -> Result<Channel, Error> {
+    let channel = match &config.address {
+        Some(address) => Channel::open_private(address).and_then(|mut channel| {
+            channel.register()?;
+            Ok(channel)
+        }),
+        None => Channel::get_private(BusType::Session),
+    };
+    let mut channel = channel.map_err(|error| Error::NoSessionBus(Box::new(error)))?;
+    channel.set_watch_enabled(true);
+    Ok(channel)
+}
+
+fn setting_changed_rule(config: &Config, filter: &Filter) -> String {
+    let match_rule = MatchRule::new_signal(INTERFACE, SETTING_CHANGED)
+        .with_sender(&*config.destination)
+        .with_path(&*config.path)
+        .match_str();
+    // `MatchRule` does not support filtering by arguments.
+    let namespace = quote(&filter.namespace);
+    match &filter.key {
+        Some(key) => format!("{match_rule},arg0={namespace},arg1={}", quote(key)),
+        None => format!("{match_rule},arg0={namespace}"),
+    }
+}
+
+fn name_owner_changed_rule(config: &Config) -> String {
+    format!(
+        "type='signal',sender='{BUS}',path='/org/freedesktop/DBus',interface='{BUS}',member='\
+         {NAME_OWNER_CHANGED}',arg0={}",
+        quote(&config.destination)
+    )
+}
+
+/// Quotes `value` for a match rule, apostrophes can only be escaped outside of
+/// quotes.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn add_match(channel: &Channel, config: &Config, match_rule: String) -> Result<String, Error> {
+    bus(channel, config.timeout).method_call::<(), _, _, _>(BUS, "AddMatch", (&match_rule,))?;
+    Ok(match_rule)
+}
+
+fn bus(channel: &Channel, timeout: Duration) -> Proxy<'_, &Channel> {
+    Proxy::new(BUS, "/org/freedesktop/DBus", timeout, channel)
+}
+
+/// Blocking receiver of [`Signal`]s.
+pub(crate) struct Listener {
+    channel: Channel,
+    match_rules: Vec<String>,
+    config: Arc<Config>,
+    stopper: Stopper,
+}
+
+impl Listener {
+    /// Returns the next [`Signal`], or `None` when stopped, disconnected or at
+    /// `deadline`.
+    ///
+    /// Errors when a signal cannot be parsed.
+    pub(crate) fn next(&mut self, deadline: Option<Instant>) -> Option<Result<Signal, Error>> {
+        loop {
+            if self.stopper.is_stopped() {
+                return None;
+            }
+            let Ok(message) = self.pop_message() else {
+                return None;
+            };
+            let Some(message) = message else {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                    || !self.wait(deadline)
+                {
+                    return None;
+                }
+                continue;
+            };
+            if let Some(setting_changed) = setting_changed(&message) {
+                return Some(setting_changed.map(Signal::SettingChanged));
+            } else if let Some(has_owner) = name_owner_changed(&message, &self.config) {
+                return Some(Ok(Signal::OwnerChanged(has_owner)));
+            } else if let Some(reply) = default_reply(&message) {
+                _ = self.channel.send(reply);
+            }
+        }
+    }
+
+    /// Returns a queued or already received message without blocking, errors
+    /// when disconnected.
+    fn pop_message(&self) -> Result<Option<Message>, ()> {
+        if let Some(message) = self.channel.pop_message() {
+            return Ok(Some(message));
+        }
+        self.channel.read_write(Some(Duration::ZERO))?;
+        Ok(self.channel.pop_message())
+    }
+
+    /// Waits for incoming messages, a call to [`Stopper::stop`] or `deadline`,
+    /// returns `false` when disconnected.
+    ///
+    /// Messages already read from the socket are not reported, check with
+    /// [`Self::pop_message`] first.
+    #[cfg(unix)]
+    fn wait(&self, deadline: Option<Instant>) -> bool {
+        if self.channel.has_messages_to_send() {
+            // Let libdbus block until everything is written, as we are not
+            // polling for the socket to become writable.
+            self.channel.flush();
+        }
+        let mut fds = [
+            libc::pollfd {
+                fd: self.channel.watch().fd,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: self.stopper.wake_up.0.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        let timeout = deadline.map_or(-1, |deadline| {
+            poll_timeout(deadline.saturating_duration_since(Instant::now()))
+        });
+        // SAFETY: `fds` is a valid array of two `pollfd`s.
+        unsafe { libc::poll(fds.as_mut_ptr(), 2, timeout) };
+        self.channel.read_write(Some(Duration::ZERO)).is_ok()
+    }
+
+    /// Waits for incoming messages or `deadline`, returns `false` when
+    /// disconnected.
+    ///
+    /// Without a way to interrupt it, this times out regularly to check for
+    /// [`Stopper::stop`].
+    #[cfg(not(unix))]
+    fn wait(&self, deadline: Option<Instant>) -> bool {
+        let timeout = deadline.map_or(STOP_INTERVAL, |deadline| {
+            STOP_INTERVAL.min(deadline.saturating_duration_since(Instant::now()))
+        });
+        self.channel.read_write(Some(timeout)).is_ok()
+    }
+
+    /// Removes the match rules from the bus.
+    pub(crate) fn close(self) -> Result<(), Error> {
+        for match_rule in self.match_rules {
+            bus(&self.channel, self.config.timeout).method_call::<(), _, _, _>(
+                BUS,
+                "RemoveMatch",
+                (match_rule,),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Interval to check for [`Stopper::stop`] without a way to be woken up.
+#[cfg(not(unix))]
+const STOP_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Handle to stop [`Listener`]s and waits from another thread.
+#[derive(Clone)]
+pub(crate) struct Stopper {
+    stopped: Arc<AtomicBool>,
+    /// Read and write end, the read end becomes readable when stopped.
+    #[cfg(unix)]
+    wake_up: Arc<(UnixStream, UnixStream)>,
+}
+
+impl Stopper {
+    pub(crate) fn new() -> Result<Self, Error> {
+        Ok(Self {
+            stopped: Arc::default(),
+            #[cfg(unix)]
+            wake_up: Arc::new(UnixStream::pair()?),
+        })
+    }
+
+    pub(crate) fn stop(&self) {
+        self.stopped.store(true, Ordering::Release);
+        #[cfg(unix)]
+        let _ = (&self.wake_up.1).write(&[0]);
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    /// Waits for `timeout` or until stopped, returns whether it was stopped.
+    #[cfg(unix)]
+    pub(crate) fn wait(&self, timeout: Duration) -> bool {
+        let mut fd = libc::pollfd {
+            fd: self.wake_up.0.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `fd` is a valid `pollfd`.
+        unsafe { libc::poll(std::ptr::addr_of_mut!(fd), 1, poll_timeout(timeout)) };
+        self.is_stopped()
+    }
+
+    /// Waits for `timeout` or until stopped, returns whether it was stopped.
+    #[cfg(not(unix))]
+    pub(crate) fn wait(&self, timeout: Duration) -> bool {
+        let deadline = std::time::Instant::now() + timeout;
+        while !self.is_stopped() {
+            let left = deadline.saturating_duration_since(std::time::Instant::now());
+            if left.is_zero() {
+                return false;
+            }
+            std::thread::sleep(left.min(STOP_INTERVAL));
+        }
+        true
+    }
+}
+
+/// Stream of [`SettingChanged`] signals, driven by the [`async_io`] reactor.
+#[cfg(all(feature = "stream", unix))]
+pub(crate) struct SettingStream {
+    // Needs to be dropped before the `channel` owning the file descriptor.
+    watch: Async<WatchFd>,
+    channel: Channel,
+}
+
+#[cfg(all(feature = "stream", unix))]
+impl Stream for SettingStream {
+    type Item = SettingChanged;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if self.channel.read_write(Some(Duration::ZERO)).is_err() {
+                return Poll::Ready(None);
+            }
+            while let Some(message) = self.channel.pop_message() {
+                if let Some(Ok(setting_changed)) = setting_changed(&message) {
+                    return Poll::Ready(Some(setting_changed));
+                } else if let Some(reply) = default_reply(&message) {
+                    _ = self.channel.send(reply);
+                }
+            }
+            match self.watch.poll_readable(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(_)) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// File descriptor of a [`Channel`], which stays owned by the channel.
+#[cfg(all(feature = "stream", unix))]
+struct WatchFd(RawFd);
+
+#[cfg(all(feature = "stream", unix))]
+impl AsFd for WatchFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // SAFETY: `SettingStream` drops the `Async<WatchFd>` before the `Channel`
+        // owning the file descriptor.
+        unsafe { BorrowedFd::borrow_raw(self.0) }
+    }
+}
+
+/// Converts to milliseconds for `poll`, rounding up to not wake up early.
+#[cfg(unix)]
+fn poll_timeout(timeout: Duration) -> libc::c_int {
+    timeout
+        .as_micros()
+        .div_ceil(1000)
+        .try_into()
+        .unwrap_or(libc::c_int::MAX)
+}
+
+impl From<dbus::Error> for Error {
+    fn from(error: dbus::Error) -> Self {
+        Error::dbus(name(&error).as_deref(), error)
+    }
+}
+
+fn name(error: &dbus::Error) -> Option<String> {
+    error.name().map(str::to_owned)
+}
+
+/// Returns whether the portal has an owner now, if `message` is a
+/// `NameOwnerChanged` signal for it.
+fn name_owner_changed(message: &Message, config: &Config) -> Option<bool> {
+    if message.msg_type() != MessageType::Signal
+        || message.interface().as_deref() != Some(BUS)
+        || message.member().as_deref() != Some(NAME_OWNER_CHANGED)
+    {
+        return None;
+    }
+    let (name, _, new_owner) = message.read3::<&str, &str, &str>().ok()?;
+    (name == config.destination).then_some(!new_owner.is_empty())
+}
+
+/// Parses `message` if it is a `SettingChanged` signal.
+fn setting_changed(message: &Message) -> Option<Result<SettingChanged, Error>> {
+    if message.msg_type() != MessageType::Signal
+        || message.interface().as_deref() != Some(INTERFACE)
+        || message.member().as_deref() != Some(SETTING_CHANGED)
+    {
+        return None;
+    }
+    Some(
+        message
+            .read3::<String, String, Variant<Box<dyn RefArg>>>()
+            .map(|(namespace, key, value)| SettingChanged {
+                namespace,
+                key,
+                value: to_value(&value),
+            })
+            .map_err(|error| Error::DBus(Box::new(error))),
+    )
+}
+
+fn to_value(arg: &dyn RefArg) -> Option<Value> {
+    Some(match arg.arg_type() {
+        ArgType::Boolean => Value::Bool(arg.as_u64()? != 0),
+        ArgType::Byte | ArgType::UInt16 | ArgType::UInt32 | ArgType::UInt64 => {
+            Value::U64(arg.as_u64()?)
+        }
+        ArgType::Int16 | ArgType::Int32 | ArgType::Int64 => Value::I64(arg.as_i64()?),
+        ArgType::Double => Value::F64(arg.as_f64()?),
+        ArgType::String | ArgType::ObjectPath | ArgType::Signature => {
+            Value::String(arg.as_str()?.to_owned())
+        }
+        ArgType::Variant => to_value(arg.as_iter()?.next()?)?,
+        ArgType::Array if arg.signature().starts_with("a{") => {
+            let mut entries = arg.as_iter()?;
+            let mut dict = Vec::new();
+            while let (Some(key), Some(value)) = (entries.next(), entries.next()) {
+                dict.push((to_value(key)?, to_value(value)?));
+            }
+            Value::Dict(dict)
+        }
+        ArgType::Array => Value::Array(arg.as_iter()?.map(to_value).collect::<Option<_>>()?),
+        ArgType::Struct => Value::Struct(arg.as_iter()?.map(to_value).collect::<Option<_>>()?),
+        ArgType::DictEntry | ArgType::UnixFd | ArgType::Invalid => return None,
+    })
+}
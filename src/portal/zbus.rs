@@ -0,0 +1,354 @@
+//! Pure-Rust backend using `zbus`.
+
+use std::collections::HashMap;
+#[cfg(all(feature = "stream", unix))]
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+#[cfg(all(feature = "stream", unix))]
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use async_io::Timer;
+use event_listener::{Event, Listener as _};
+#[cfg(all(feature = "stream", unix))]
+use futures_core::Stream;
+use futures_lite::stream::Or;
+use futures_lite::{future, StreamExt};
+use zbus::blocking::connection::Builder;
+use zbus::blocking::proxy;
+use zbus::message::Type;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{self, OwnedValue};
+use zbus::{DBusError, MatchRule, Message, MessageStream};
+
+use super::{
+    Config, Filter, Namespaces, SettingChanged, Signal, Value, BUS, INTERFACE, NAME_OWNER_CHANGED,
+    SETTING_CHANGED, UNKNOWN_METHOD,
+};
+use crate::Error;
+
+#[derive(Clone)]
+pub(crate) struct Connection {
+    connection: zbus::blocking::Connection,
+    config: Arc<Config>,
+}
+
+impl Connection {
+    pub(crate) fn new(config: Config) -> Result<Self, Error> {
+        let builder = match &config.address {
+            Some(address) => Builder::address(&**address),
+            None => Builder::session(),
+        };
+        let connection = builder
+            .and_then(|builder| builder.method_timeout(config.timeout).build())
+            .map_err(|error| Error::NoSessionBus(Box::new(error)))?;
+        Ok(Self::with_connection(connection, config))
+    }
+
+    pub(crate) fn with_connection(connection: zbus::blocking::Connection, config: Config) -> Self {
+        Self {
+            connection,
+            config: Arc::new(config),
+        }
+    }
+
+    fn portal(&self) -> Result<zbus::blocking::Proxy<'_>, Error> {
+        Ok(proxy::Builder::new(&self.connection)
+            .destination(&*self.config.destination)?
+            .path(&*self.config.path)?
+            .interface(INTERFACE)?
+            .cache_properties(CacheProperties::No)
+            .build()?)
+    }
+
+    pub(crate) fn read(&self, namespace: &str, key: &str) -> Result<Option<Value>, Error> {
+        let portal = self.portal()?;
+        match portal.call::<_, _, OwnedValue>("ReadOne", &(namespace, key)) {
+            Ok(value) => Ok(to_value(&value)),
+            // Version 1 only supports `Read`, which wraps the value in an additional variant.
+            Err(error) if name(&error).as_deref() == Some(UNKNOWN_METHOD) => {
+                let value: OwnedValue = portal
+                    .call("Read", &(namespace, key))
+                    .map_err(|error| Error::read(name(&error).as_deref(), error, namespace, key))?;
+                Ok(to_value(&value))
+            }
+            Err(error) => Err(Error::read(name(&error).as_deref(), error, namespace, key)),
+        }
+    }
+
+    pub(crate) fn read_all(&self, namespaces: &[&str]) -> Result<Namespaces, Error> {
+        let namespaces: HashMap<String, HashMap<String, OwnedValue>> =
+            self.portal()?.call("ReadAll", &(namespaces,))?;
+        Ok(namespaces
+            .into_iter()
+            .map(|(namespace, settings)| {
+                let settings = settings
+                    .into_iter()
+                    .filter_map(|(key, value)| Some((key, to_value(&value)?)))
+                    .collect();
+                (namespace, settings)
+            })
+            .collect())
+    }
+
+    fn messages(&self, match_rule: MatchRule<'_>) -> Result<MessageStream, Error> {
+        Ok(future::block_on(MessageStream::for_match_rule(
+            match_rule,
+            self.connection.inner(),
+            None,
+        ))?)
+    }
+
+    fn setting_changed(&self, filter: &Filter) -> Result<MessageStream, Error> {
+        let mut match_rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .sender(&*self.config.destination)?
+            .path(&*self.config.path)?
+            .interface(INTERFACE)?
+            .member(SETTING_CHANGED)?;
+        // `zbus` does not escape apostrophes in match rules, such arguments
+        // are only filtered on our side.
+        let escapable = |argument: &str| !argument.contains('\'');
+        if escapable(&filter.namespace) {
+            match_rule = match_rule.arg(0, &*filter.namespace)?;
+            if let Some(key) = filter.key.as_deref().filter(|key| escapable(key)) {
+                match_rule = match_rule.arg(1, key)?;
+            }
+        }
+        self.messages(match_rule.build())
+    }
+
+    pub(crate) fn listen(&self, filter: &Filter, stopper: &Stopper) -> Result<Listener, Error> {
+        let name_owner_changed = self.messages(
+            MatchRule::builder()
+                .msg_type(Type::Signal)
+                .sender(BUS)?
+                .interface(BUS)?
+                .member(NAME_OWNER_CHANGED)?
+                .arg(0, &*self.config.destination)?
+                .build(),
+        )?;
+        Ok(Listener {
+            messages: self.setting_changed(filter)?.or(name_owner_changed),
+            config: self.config.clone(),
+            stopper: stopper.clone(),
+        })
+    }
+
+    /// Opens a new connection with the same configuration, e.g., after this
+    /// one was disconnected.
+    pub(crate) fn reconnect(&self) -> Result<Self, Error> {
+        Self::new((*self.config).clone())
+    }
+
+    #[cfg(all(feature = "stream", unix))]
+    pub(crate) fn stream(&self, filter: &Filter) -> Result<SettingStream, Error> {
+        Ok(SettingStream(self.setting_changed(filter)?))
+    }
+}
+
+/// Blocking receiver of [`Signal`]s.
+pub(crate) struct Listener {
+    messages: Or<MessageStream, MessageStream>,
+    config: Arc<Config>,
+    stopper: Stopper,
+}
+
+impl Listener {
+    /// Returns the next [`Signal`], or `None` when stopped, disconnected or at
+    /// `deadline`.
+    ///
+    /// Errors when a message cannot be received or parsed.
+    pub(crate) fn next(&mut self, deadline: Option<Instant>) -> Option<Result<Signal, Error>> {
+        let next = async {
+            loop {
+                let message = match self.messages.next().await? {
+                    Ok(message) => message,
+                    // The connection was lost.
+                    Err(zbus::Error::InputOutput(_)) => return None,
+                    Err(error) => return Some(Err(error.into())),
+                };
+                if let Some(setting_changed) = setting_changed(&message) {
+                    return Some(setting_changed.map(Signal::SettingChanged));
+                } else if let Some(has_owner) = name_owner_changed(&message, &self.config) {
+                    return Some(Ok(Signal::OwnerChanged(has_owner)));
+                }
+            }
+        };
+        let timeout = async {
+            match deadline {
+                Some(deadline) => _ = Timer::at(deadline).await,
+                None => future::pending().await,
+            }
+            None
+        };
+        future::block_on(future::or(
+            future::or(self.stopper.stopped(), timeout),
+            next,
+        ))
+    }
+
+    /// Removes the match rules from the bus.
+    #[allow(clippy::unnecessary_wraps)]
+    pub(crate) fn close(self) -> Result<(), Error> {
+        // Dropping the last stream for a match rule removes it.
+        drop(self.messages);
+        Ok(())
+    }
+}
+
+/// Handle to stop [`Listener`]s and waits from another thread.
+#[derive(Clone, Default)]
+pub(crate) struct Stopper(Arc<(AtomicBool, Event)>);
+
+impl Stopper {
+    #[allow(clippy::unnecessary_wraps)]
+    pub(crate) fn new() -> Result<Self, Error> {
+        Ok(Self::default())
+    }
+
+    pub(crate) fn stop(&self) {
+        let (stopped, event) = &*self.0;
+        stopped.store(true, Ordering::Release);
+        event.notify(usize::MAX);
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.0 .0.load(Ordering::Acquire)
+    }
+
+    /// Waits for `timeout` or until stopped, returns whether it was stopped.
+    pub(crate) fn wait(&self, timeout: Duration) -> bool {
+        let listener = self.0 .1.listen();
+        if !self.is_stopped() {
+            listener.wait_timeout(timeout);
+        }
+        self.is_stopped()
+    }
+
+    async fn stopped(&self) -> Option<Result<Signal, Error>> {
+        let (stopped, event) = &*self.0;
+        loop {
+            if stopped.load(Ordering::Acquire) {
+                return None;
+            }
+            let listener = event.listen();
+            if stopped.load(Ordering::Acquire) {
+                return None;
+            }
+            listener.await;
+        }
+    }
+}
+
+/// Stream of [`SettingChanged`] signals.
+#[cfg(all(feature = "stream", unix))]
+pub(crate) struct SettingStream(MessageStream);
+
+#[cfg(all(feature = "stream", unix))]
+impl Stream for SettingStream {
+    type Item = SettingChanged;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.0.poll_next(cx) {
+                Poll::Ready(Some(Ok(message))) => {
+                    if let Some(Ok(setting_changed)) = setting_changed(&message) {
+                        return Poll::Ready(Some(setting_changed));
+                    }
+                }
+                Poll::Ready(Some(Err(_))) => {}
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl From<zbus::Error> for Error {
+    fn from(error: zbus::Error) -> Self {
+        Error::dbus(name(&error).as_deref(), error)
+    }
+}
+
+fn name(error: &zbus::Error) -> Option<String> {
+    match error {
+        zbus::Error::MethodError(name, ..) => Some(name.to_string()),
+        zbus::Error::FDO(error) => Some(error.name().to_string()),
+        _ => None,
+    }
+}
+
+/// Returns whether the portal has an owner now, if `message` is a
+/// `NameOwnerChanged` signal for it.
+fn name_owner_changed(message: &Message, config: &Config) -> Option<bool> {
+    let header = message.header();
+    if header.message_type() != Type::Signal
+        || header.interface()?.as_str() != BUS
+        || header.member()?.as_str() != NAME_OWNER_CHANGED
+    {
+        return None;
+    }
+    let body = message.body();
+    let (name, _, new_owner) = body.deserialize::<(&str, &str, &str)>().ok()?;
+    (name == config.destination).then_some(!new_owner.is_empty())
+}
+
+/// Parses `message` if it is a `SettingChanged` signal.
+fn setting_changed(message: &Message) -> Option<Result<SettingChanged, Error>> {
+    let header = message.header();
+    if header.message_type() != Type::Signal
+        || header.interface()?.as_str() != INTERFACE
+        || header.member()?.as_str() != SETTING_CHANGED
+    {
+        return None;
+    }
+    Some(
+        message
+            .body()
+            .deserialize::<(String, String, OwnedValue)>()
+            .map(|(namespace, key, value)| SettingChanged {
+                namespace,
+                key,
+                value: to_value(&value),
+            })
+            .map_err(Error::from),
+    )
+}
+
+#[allow(clippy::match_wildcard_for_single_variants)]
+fn to_value(value: &zvariant::Value<'_>) -> Option<Value> {
+    use zvariant::Value as V;
+    Some(match value {
+        V::Bool(value) => Value::Bool(*value),
+        V::U8(value) => Value::U64((*value).into()),
+        V::U16(value) => Value::U64((*value).into()),
+        V::U32(value) => Value::U64((*value).into()),
+        V::U64(value) => Value::U64(*value),
+        V::I16(value) => Value::I64((*value).into()),
+        V::I32(value) => Value::I64((*value).into()),
+        V::I64(value) => Value::I64(*value),
+        V::F64(value) => Value::F64(*value),
+        V::Str(value) => Value::String(value.to_string()),
+        V::Signature(value) => Value::String(value.to_string()),
+        V::ObjectPath(value) => Value::String(value.to_string()),
+        V::Value(value) => to_value(value)?,
+        V::Array(values) => Value::Array(values.iter().map(to_value).collect::<Option<_>>()?),
+        V::Dict(entries) => Value::Dict(
+            entries
+                .iter()
+                .map(|(key, value)| Some((to_value(key)?, to_value(value)?)))
+                .collect::<Option<_>>()?,
+        ),
+        V::Structure(fields) => Value::Struct(
+            fields
+                .fields()
+                .iter()
+                .map(to_value)
+                .collect::<Option<_>>()?,
+        ),
+        // `Fd`, and `Maybe` when `zvariant/gvariant` is enabled.
+        _ => return None,
+    })
+}
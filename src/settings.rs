@@ -0,0 +1,109 @@
+use std::any::type_name;
+
+use crate::portal::{Config, Connection, Filter, Namespaces};
+use crate::subscription::subscribe_with;
+use crate::{Error, Subscription, Value};
+
+/// Client for arbitrary settings of the portal.
+///
+/// Unlike [`detect`](crate::detect) and friends, this is not limited to the
+/// `org.freedesktop.appearance` namespace, e.g., also supporting
+/// `org.gnome.desktop.interface` or `org.kde.kdeglobals.General`. It works with
+/// both versions of the portal, i.e., falls back to `Read` when `ReadOne` is
+/// not available.
+///
+/// Use [`Client::settings`](crate::Client::settings) to configure the
+/// connection.
+pub struct Settings {
+    connection: Connection,
+}
+
+impl Settings {
+    /// Connects to the portal on the session bus.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the session bus cannot be reached.
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self::with_connection(Connection::new(Config::default())?))
+    }
+
+    pub(crate) fn with_connection(connection: Connection) -> Self {
+        Self { connection }
+    }
+
+    /// Reads the setting `key` in `namespace`.
+    ///
+    /// Use [`Value`] as `T` to get the value without conversion.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached, the setting does not exist,
+    /// or its value cannot be converted to `T`.
+    pub fn read_one<T: TryFrom<Value>>(&self, namespace: &str, key: &str) -> Result<T, Error> {
+        convert(self.connection.read(namespace, key)?)
+    }
+
+    /// Reads all settings in `namespaces`, returned by key, by namespace.
+    ///
+    /// Namespaces can end with `*` to match all namespaces with that prefix,
+    /// and an empty slice returns all settings.
+    ///
+    /// Settings with values of types that cannot be represented by [`Value`]
+    /// are omitted.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached.
+    pub fn read_all(&self, namespaces: &[&str]) -> Result<Namespaces, Error> {
+        self.connection.read_all(namespaces)
+    }
+
+    /// Calls `call_back` with the current value of `key` in `namespace` and
+    /// every time it changes, see [`subscribe`](crate::subscribe).
+    ///
+    /// Changes to a value that cannot be converted to `T` are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the portal cannot be reached, the setting does not exist,
+    /// or its value cannot be converted to `T`.
+    pub fn watch<T: TryFrom<Value> + Clone + Send + 'static>(
+        &self,
+        namespace: &str,
+        key: &str,
+        call_back: impl FnMut(T) + Send + 'static,
+    ) -> Result<Subscription, Error> {
+        let (namespace, key) = (namespace.to_owned(), key.to_owned());
+        let read = {
+            let (namespace, key) = (namespace.clone(), key.clone());
+            move |connection: &Connection| convert(connection.read(&namespace, &key)?)
+        };
+        subscribe_with(
+            &self.connection,
+            Filter::setting(&namespace, &key),
+            read,
+            move |current, setting_changed| {
+                if !setting_changed.is(&namespace, &key) {
+                    return Ok(false);
+                }
+                *current = convert(setting_changed.value().cloned())?;
+                Ok(true)
+            },
+            call_back,
+        )
+    }
+}
+
+fn convert<T: TryFrom<Value>>(value: Option<Value>) -> Result<T, Error> {
+    let Some(value) = value else {
+        return Err(Error::UnexpectedValue {
+            expected: type_name::<T>(),
+            found: None,
+        });
+    };
+    T::try_from(value.clone()).map_err(|_| Error::UnexpectedValue {
+        expected: type_name::<T>(),
+        found: Some(value),
+    })
+}
@@ -0,0 +1,78 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::portal::SettingStream;
+use crate::{env, Client, Error, Mode};
+
+/// Returns a [`Stream`] yielding the current [`Mode`] and every change to it.
+///
+/// Instead of a background thread per stream, the connection is driven by the
+/// shared reactor of [`async_io`], making it usable with any async runtime.
+///
+/// Setting up the stream, i.e., connecting to the bus and reading the current
+/// mode, is blocking, same as [`detect`](crate::detect).
+///
+/// Watches only the portal, unless [overridden](crate::detect).
+///
+/// # Errors
+///
+/// Errors when the session bus or the portal cannot be reached.
+pub fn stream() -> Result<ModeStream, Error> {
+    if let Some(mode) = env::mode() {
+        return Ok(ModeStream::fixed(mode));
+    }
+    Client::global()?.stream()
+}
+
+/// [`Stream`] of [`Mode`] changes, created by [`stream`].
+///
+/// The stream ends when the connection to the bus is lost.
+#[must_use = "streams do nothing unless polled"]
+pub struct ModeStream {
+    initial: Option<Mode>,
+    /// `None` for a mode that never changes.
+    changes: Option<SettingStream>,
+}
+
+impl ModeStream {
+    pub(crate) fn new(initial: Mode, changes: SettingStream) -> Self {
+        Self {
+            initial: Some(initial),
+            changes: Some(changes),
+        }
+    }
+
+    /// Returns a stream yielding `mode` once, and then staying pending.
+    fn fixed(mode: Mode) -> Self {
+        Self {
+            initial: Some(mode),
+            changes: None,
+        }
+    }
+}
+
+impl Stream for ModeStream {
+    type Item = Mode;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(mode) = self.initial.take() {
+            return Poll::Ready(Some(mode));
+        }
+        let Some(changes) = &mut self.changes else {
+            return Poll::Pending;
+        };
+        loop {
+            match Pin::new(&mut *changes).poll_next(cx) {
+                Poll::Ready(Some(setting_changed)) => {
+                    if let Some(Ok(mode)) = setting_changed.get() {
+                        return Poll::Ready(Some(mode));
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
@@ -0,0 +1,479 @@
+use std::panic::AssertUnwindSafe;
+use std::sync::{Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::portal::{Connection, Filter, Listener, Setting, SettingChanged, Signal, Stopper};
+use crate::{env, Client, Detector, Error, Hub, Mode};
+
+/// Delays between attempts to reconnect to the bus.
+const INITIAL_DELAY: Duration = Duration::from_millis(100);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Calls `call_back` with the current [`Mode`] and every time it changes.
+///
+/// The changes are received on a background thread, which runs until the
+/// returned [`Subscription`] is dropped or
+/// [`unsubscribed`](Subscription::unsubscribe).
+///
+/// The mode is read again when the portal restarts, and the connection is
+/// reestablished when it is lost, use [`subscribe_events`] to be notified of
+/// these and of errors, which are ignored here.
+///
+/// Every change is delivered, even if the mode stayed the same, use
+/// [`Subscription::builder`] to deduplicate them.
+///
+/// When the portal is not available, or reports no preference while a fallback
+/// has one, the configuration files of the desktop are watched instead, see
+/// [`detect`](crate::detect), delivering only actual changes.
+///
+/// When the mode is overridden through the environment, `call_back` is only
+/// called once with it, see [`detect`](crate::detect).
+///
+/// # Errors
+///
+/// Errors like [`detect`](crate::detect).
+pub fn subscribe(mut call_back: impl FnMut(Mode) + Send + 'static) -> Result<Subscription, Error> {
+    if let Some(mode) = env::mode() {
+        call_back(mode);
+        return Ok(Subscription::fixed());
+    }
+    Detector::default().subscribe(call_back)
+}
+
+/// Calls `call_back` with the current [`Mode`] and every time it changes, and
+/// with the [`Event`]s of the subscription, see [`subscribe`].
+///
+/// Watches only the portal, unless [overridden](crate::detect).
+///
+/// # Errors
+///
+/// Errors when the session bus or the portal cannot be reached.
+pub fn subscribe_events(
+    call_back: impl FnMut(Event) + Send + 'static,
+) -> Result<Subscription, Error> {
+    Subscription::builder()
+        .deduplicate(false)
+        .subscribe_events(call_back)
+}
+
+/// Event delivered by [`subscribe_events`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Event {
+    /// The current mode, delivered initially and every time it changes.
+    ModeChanged(Mode),
+    /// Something went wrong while tracking the mode, e.g., a signal could not
+    /// be parsed, the mode changed to a value of an unexpected type, or the
+    /// connection was lost and reconnecting failed.
+    ///
+    /// The subscription keeps running, with the last known mode.
+    Error(Error),
+    /// The portal has no owner, e.g., because it is restarting.
+    ///
+    /// The mode is delivered again once the portal is back.
+    PortalUnavailable,
+    /// The connection to the bus was lost and has been reestablished, followed
+    /// by the current mode.
+    Reconnected,
+}
+
+impl From<Update<Mode>> for Event {
+    fn from(update: Update<Mode>) -> Self {
+        match update {
+            Update::Value(mode) => Event::ModeChanged(mode),
+            Update::Error(error) => Event::Error(error),
+            Update::PortalUnavailable => Event::PortalUnavailable,
+            Update::Reconnected => Event::Reconnected,
+        }
+    }
+}
+
+pub(crate) fn subscribe_setting<T: Setting + Clone + Send + 'static>(
+    connection: &Connection,
+    call_back: impl FnMut(T) + Send + 'static,
+) -> Result<Subscription, Error> {
+    subscribe_with(
+        connection,
+        Filter::of::<T>(),
+        T::read,
+        update_setting,
+        call_back,
+    )
+}
+
+/// Updates `current` if `setting_changed` is for `T`.
+pub(crate) fn update_setting<T: Setting>(
+    current: &mut T,
+    setting_changed: &SettingChanged,
+) -> Result<bool, Error> {
+    let Some(value) = setting_changed.get().transpose()? else {
+        return Ok(false);
+    };
+    *current = value;
+    Ok(true)
+}
+
+/// Calls `call_back` with the value returned by `read`, and again every time
+/// `update` returns `true` for a [`SettingChanged`] or the value is read again
+/// after the portal restarted or the connection was reestablished.
+///
+/// Errors while doing so are ignored.
+pub(crate) fn subscribe_with<T: Clone + Send + 'static>(
+    connection: &Connection,
+    filter: Filter,
+    read: impl Fn(&Connection) -> Result<T, Error> + Send + 'static,
+    update: impl FnMut(&mut T, &SettingChanged) -> Result<bool, Error> + Send + 'static,
+    mut call_back: impl FnMut(T) + Send + 'static,
+) -> Result<Subscription, Error> {
+    watch(connection, filter, read, update, None, move |update| {
+        if let Update::Value(value) = update {
+            call_back(value);
+        }
+    })
+}
+
+/// Update delivered by [`watch`].
+pub(crate) enum Update<T> {
+    Value(T),
+    Error(Error),
+    /// The portal lost its owner, or could not be reached after it changed.
+    PortalUnavailable,
+    /// The connection to the bus was lost and has been reestablished.
+    Reconnected,
+}
+
+/// Like [`subscribe_with`], but also reports errors, when the portal becomes
+/// unavailable and when the connection was reestablished.
+///
+/// When the portal's bus name gets a new owner, the value is read again. When
+/// the connection is lost, reconnecting is retried with an exponential
+/// backoff.
+///
+/// With a `debounce` window, values after the initial one are only delivered
+/// once no other value followed within the window.
+pub(crate) fn watch<T: Clone + Send + 'static>(
+    connection: &Connection,
+    filter: Filter,
+    read: impl Fn(&Connection) -> Result<T, Error> + Send + 'static,
+    mut update: impl FnMut(&mut T, &SettingChanged) -> Result<bool, Error> + Send + 'static,
+    debounce: Option<Duration>,
+    mut call_back: impl FnMut(Update<T>) + Send + 'static,
+) -> Result<Subscription, Error> {
+    let stopper = Stopper::new()?;
+    let mut listener = connection.listen(&filter, &stopper)?;
+    let mut current = read(connection)?;
+    call_back(Update::Value(current.clone()));
+
+    let mut connection = connection.clone();
+    let mut delivery = Delivery {
+        call_back,
+        debounce,
+        pending: None,
+    };
+    let thread = {
+        let stopper = stopper.clone();
+        thread::spawn(move || loop {
+            let deadline = delivery.deadline();
+            match listener.next(deadline) {
+                Some(Ok(Signal::SettingChanged(setting_changed))) => {
+                    match update(&mut current, &setting_changed) {
+                        Ok(true) => delivery.send(Update::Value(current.clone())),
+                        Ok(false) => {}
+                        Err(error) => delivery.send(Update::Error(error)),
+                    }
+                }
+                Some(Ok(Signal::OwnerChanged(true))) => {
+                    delivery.send(read_again(&read, &connection, &mut current));
+                }
+                Some(Ok(Signal::OwnerChanged(false))) => delivery.send(Update::PortalUnavailable),
+                Some(Err(error)) => delivery.send(Update::Error(error)),
+                None if stopper.is_stopped() => return listener.close(),
+                None if deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+                    delivery.flush();
+                }
+                None => {
+                    delivery.send(Update::Error(Error::Disconnected));
+                    let Some(reconnected) =
+                        reconnect(&connection, &filter, &stopper, &mut |update| {
+                            delivery.send(update);
+                        })
+                    else {
+                        return Ok(());
+                    };
+                    (connection, listener) = reconnected;
+                    delivery.send(Update::Reconnected);
+                    delivery.send(read_again(&read, &connection, &mut current));
+                }
+            }
+        })
+    };
+
+    Ok(Subscription::new(stopper, thread))
+}
+
+/// Passes updates on to the callback, holding values back for the debounce
+/// window.
+struct Delivery<T, F> {
+    call_back: F,
+    debounce: Option<Duration>,
+    /// Value held back and when to deliver it.
+    pending: Option<(T, Instant)>,
+}
+
+impl<T, F: FnMut(Update<T>)> Delivery<T, F> {
+    fn send(&mut self, update: Update<T>) {
+        match (update, self.debounce) {
+            (Update::Value(value), Some(debounce)) => {
+                self.pending = Some((value, Instant::now() + debounce));
+            }
+            (update, _) => (self.call_back)(update),
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.pending.as_ref().map(|(_, deadline)| *deadline)
+    }
+
+    fn flush(&mut self) {
+        if let Some((value, _)) = self.pending.take() {
+            (self.call_back)(Update::Value(value));
+        }
+    }
+}
+
+/// Reads the value again, returns the update to deliver.
+fn read_again<T: Clone>(
+    read: &impl Fn(&Connection) -> Result<T, Error>,
+    connection: &Connection,
+    current: &mut T,
+) -> Update<T> {
+    match read(connection) {
+        Ok(value) => {
+            *current = value;
+            Update::Value(current.clone())
+        }
+        Err(Error::PortalUnavailable(_)) => Update::PortalUnavailable,
+        Err(error) => Update::Error(error),
+    }
+}
+
+/// Reconnects with an exponential backoff, reporting failed attempts, returns
+/// `None` when stopped.
+fn reconnect<T>(
+    connection: &Connection,
+    filter: &Filter,
+    stopper: &Stopper,
+    call_back: &mut impl FnMut(Update<T>),
+) -> Option<(Connection, Listener)> {
+    let mut delay = INITIAL_DELAY;
+    loop {
+        if stopper.wait(delay) {
+            return None;
+        }
+        match connection
+            .reconnect()
+            .and_then(|connection| Ok((connection.listen(filter, stopper)?, connection)))
+        {
+            Ok((listener, connection)) => return Some((connection, listener)),
+            Err(error) => call_back(Update::Error(error)),
+        }
+        delay = (delay * 2).min(MAX_DELAY);
+    }
+}
+
+/// Builder for a subscription to the [`Mode`], created by
+/// [`Subscription::builder`].
+///
+/// Watches only the portal, unless [overridden](crate::detect).
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct SubscriptionBuilder {
+    client: Option<Client>,
+    deduplicate: bool,
+    debounce: Option<Duration>,
+}
+
+impl Default for SubscriptionBuilder {
+    fn default() -> Self {
+        Self {
+            client: None,
+            deduplicate: true,
+            debounce: None,
+        }
+    }
+}
+
+impl SubscriptionBuilder {
+    /// Subscribes using `client`, defaults to the [`global`](Client::global)
+    /// client.
+    ///
+    /// The mode is then always read from the portal, ignoring an override
+    /// through the environment, same as [`Client::subscribe`].
+    pub fn client(mut self, client: &Client) -> Self {
+        self.client = Some(client.clone());
+        self
+    }
+
+    /// Sets whether to only deliver a [`Mode`] when it differs from the last
+    /// delivered one, starting with the initial mode, defaults to `true`.
+    ///
+    /// Some desktops emit the same value several times, e.g., when toggling
+    /// themes.
+    pub fn deduplicate(mut self, deduplicate: bool) -> Self {
+        self.deduplicate = deduplicate;
+        self
+    }
+
+    /// Only delivers a [`Mode`] once it did not change for `window`, e.g., to
+    /// only apply the final mode of a burst of changes, defaults to delivering
+    /// every change immediately.
+    ///
+    /// The initial mode is always delivered immediately.
+    pub fn debounce(mut self, window: Duration) -> Self {
+        self.debounce = Some(window);
+        self
+    }
+
+    /// Calls `call_back` with the current [`Mode`] and every time it changes,
+    /// see [`subscribe`].
+    ///
+    /// # Errors
+    ///
+    /// Errors when the session bus or the portal cannot be reached.
+    pub fn subscribe(
+        self,
+        mut call_back: impl FnMut(Mode) + Send + 'static,
+    ) -> Result<Subscription, Error> {
+        self.subscribe_events(move |event| {
+            if let Event::ModeChanged(mode) = event {
+                call_back(mode);
+            }
+        })
+    }
+
+    /// Returns a [`Hub`] sharing this subscription between any number of
+    /// subscribers.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the session bus or the portal cannot be reached.
+    pub fn hub(self) -> Result<Hub, Error> {
+        Hub::with_builder(self)
+    }
+
+    /// Calls `call_back` with the current [`Mode`] and every time it changes,
+    /// and with the [`Event`]s of the subscription, see [`subscribe_events`].
+    ///
+    /// # Errors
+    ///
+    /// Errors when the session bus or the portal cannot be reached.
+    pub fn subscribe_events(
+        self,
+        mut call_back: impl FnMut(Event) + Send + 'static,
+    ) -> Result<Subscription, Error> {
+        let client = if let Some(client) = &self.client {
+            client
+        } else if let Some(mode) = env::mode() {
+            call_back(Event::ModeChanged(mode));
+            return Ok(Subscription::fixed());
+        } else {
+            Client::global()?
+        };
+        let mut last = None;
+        watch(
+            client.connection(),
+            Filter::of::<Mode>(),
+            Mode::read,
+            update_setting,
+            self.debounce,
+            move |update| {
+                if let Update::Value(mode) = update {
+                    if self.deduplicate && last.replace(mode) == Some(mode) {
+                        return;
+                    }
+                }
+                call_back(Event::from(update));
+            },
+        )
+    }
+}
+
+/// Handle to a subscription created by [`subscribe`].
+///
+/// Dropping it removes the match rule from the bus and joins the background
+/// thread, use [`unsubscribe`](Self::unsubscribe) to observe errors doing so.
+#[must_use = "dropping a `Subscription` unsubscribes immediately"]
+pub struct Subscription {
+    stop: Option<Stop>,
+}
+
+enum Stop {
+    Thread(Stopper, JoinHandle<Result<(), Error>>),
+    Custom(Mutex<Box<dyn FnOnce() -> Result<(), Error> + Send>>),
+}
+
+impl Subscription {
+    /// Returns a [`SubscriptionBuilder`] to configure a subscription to the
+    /// portal.
+    pub fn builder() -> SubscriptionBuilder {
+        SubscriptionBuilder::default()
+    }
+
+    /// Stops the subscription.
+    ///
+    /// # Errors
+    ///
+    /// Errors when the match rule could not be removed from the bus.
+    ///
+    /// # Panics
+    ///
+    /// Resumes the panic if the callback panicked.
+    pub fn unsubscribe(mut self) -> Result<(), Error> {
+        match self.stop() {
+            Some(Ok(result)) => result,
+            Some(Err(panic)) => std::panic::resume_unwind(panic),
+            None => Ok(()),
+        }
+    }
+
+    /// Wraps a subscription of a custom [`Backend`](crate::Backend), calling
+    /// `stop` when it is [`unsubscribed`](Self::unsubscribe) or dropped.
+    pub fn from_stop(stop: impl FnOnce() -> Result<(), Error> + Send + 'static) -> Self {
+        Self {
+            stop: Some(Stop::Custom(Mutex::new(Box::new(stop)))),
+        }
+    }
+
+    /// Wraps a background `thread` running until `stopper` is stopped.
+    pub(crate) fn new(stopper: Stopper, thread: JoinHandle<Result<(), Error>>) -> Self {
+        Self {
+            stop: Some(Stop::Thread(stopper, thread)),
+        }
+    }
+
+    /// Returns a subscription without a background thread, for a mode that
+    /// never changes.
+    pub(crate) fn fixed() -> Self {
+        Self { stop: None }
+    }
+
+    fn stop(&mut self) -> Option<thread::Result<Result<(), Error>>> {
+        match self.stop.take()? {
+            Stop::Thread(stopper, thread) => {
+                stopper.stop();
+                Some(thread.join())
+            }
+            Stop::Custom(stop) => {
+                let stop = stop.into_inner().unwrap_or_else(PoisonError::into_inner);
+                Some(std::panic::catch_unwind(AssertUnwindSafe(stop)))
+            }
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::hash::{BuildHasher, Hash};
+
+use crate::Mode;
+
+/// A setting's value as read from the portal, independent of the D-Bus
+/// backend.
+///
+/// Variants are unwrapped, and integers are widened to 64 bits. Use
+/// [`TryFrom`] to convert to Rust types, which returns the original value on
+/// mismatch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// `b`
+    Bool(bool),
+    /// `n`, `i` or `x`
+    I64(i64),
+    /// `y`, `q`, `u` or `t`
+    U64(u64),
+    /// `d`
+    F64(f64),
+    /// `s`, `o` or `g`
+    String(String),
+    /// `a*`
+    Array(Vec<Value>),
+    /// `a{**}`, the entries in the order they were received.
+    Dict(Vec<(Value, Value)>),
+    /// `(*)`
+    Struct(Vec<Value>),
+}
+
+impl Value {
+    pub(crate) fn as_u32(&self) -> Option<u32> {
+        match *self {
+            Value::I64(value) => value.try_into().ok(),
+            Value::U64(value) => value.try_into().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<'a>(
+            f: &mut fmt::Formatter<'_>,
+            values: impl IntoIterator<Item = &'a Value>,
+        ) -> fmt::Result {
+            for (i, value) in values.into_iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                value.fmt(f)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Value::Bool(value) => value.fmt(f),
+            Value::I64(value) => value.fmt(f),
+            Value::U64(value) => value.fmt(f),
+            Value::F64(value) => value.fmt(f),
+            Value::String(value) => write!(f, "{value:?}"),
+            Value::Array(values) => {
+                f.write_str("[")?;
+                list(f, values)?;
+                f.write_str("]")
+            }
+            Value::Dict(entries) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                f.write_str("}")
+            }
+            Value::Struct(values) => {
+                f.write_str("(")?;
+                list(f, values)?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Bool(value) => Ok(value),
+            value => Err(value),
+        }
+    }
+}
+
+macro_rules! integer {
+    ($($ty:ty),*) => {$(
+        impl TryFrom<Value> for $ty {
+            type Error = Value;
+
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                match value {
+                    Value::I64(inner) => inner.try_into().map_err(|_| value),
+                    Value::U64(inner) => inner.try_into().map_err(|_| value),
+                    value => Err(value),
+                }
+            }
+        }
+    )*};
+}
+
+integer!(u8, u16, u32, u64, i16, i32, i64);
+
+impl TryFrom<Value> for f64 {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::F64(value) => Ok(value),
+            value => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(value) => Ok(value),
+            value => Err(value),
+        }
+    }
+}
+
+impl<T: TryFrom<Value>> TryFrom<Value> for Vec<T> {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Array(ref values) => values
+                .iter()
+                .cloned()
+                .map(T::try_from)
+                .collect::<Result<_, _>>()
+                .map_err(|_| value),
+            value => Err(value),
+        }
+    }
+}
+
+impl<K, V, S> TryFrom<Value> for HashMap<K, V, S>
+where
+    K: TryFrom<Value> + Eq + Hash,
+    V: TryFrom<Value>,
+    S: BuildHasher + Default,
+{
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Dict(ref entries) => entries
+                .iter()
+                .cloned()
+                .map(|(key, value)| Some((key.try_into().ok()?, value.try_into().ok()?)))
+                .collect::<Option<_>>()
+                .ok_or(value),
+            value => Err(value),
+        }
+    }
+}
+
+macro_rules! tuple {
+    ($($ty:ident),*) => {
+        impl<$($ty: TryFrom<Value>),*> TryFrom<Value> for ($($ty,)*) {
+            type Error = Value;
+
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                let Value::Struct(ref fields) = value else {
+                    return Err(value);
+                };
+                let mut fields = fields.iter().cloned();
+                let tuple = ($($ty::try_from(fields.next().ok_or_else(|| value.clone())?)
+                    .map_err(|_| value.clone())?,)*);
+                if fields.next().is_some() {
+                    return Err(value);
+                }
+                Ok(tuple)
+            }
+        }
+    };
+}
+
+tuple!(A);
+tuple!(A, B);
+tuple!(A, B, C);
+tuple!(A, B, C, D);
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+macro_rules! from {
+    ($variant:ident: $($ty:ty),*) => {$(
+        impl From<$ty> for Value {
+            fn from(value: $ty) -> Self {
+                Value::$variant(value.into())
+            }
+        }
+    )*};
+}
+
+from!(U64: u8, u16, u32, u64);
+from!(I64: i16, i32, i64);
+from!(F64: f32, f64);
+from!(String: String, &str);
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Self {
+        Value::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<Mode> for Value {
+    /// Returns the value used for `color-scheme`.
+    fn from(mode: Mode) -> Self {
+        Value::U64(mode as u64)
+    }
+}
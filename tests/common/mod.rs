@@ -0,0 +1,57 @@
+//! Helpers for the tests of the fallbacks and overrides, which change the
+//! process' environment, so each of their files consists of a single test.
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{env, fs, process};
+
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Temporary configuration directories, removed on drop.
+pub struct Environment {
+    dir: PathBuf,
+}
+
+impl Environment {
+    /// Points `$XDG_CONFIG_HOME` and `$XDG_CONFIG_DIRS` to new temporary
+    /// directories and the session bus to a missing one, and removes the
+    /// overrides.
+    pub fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("darkmode-{name}-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        env::set_var("XDG_CONFIG_HOME", dir.join("config"));
+        env::set_var("XDG_CONFIG_DIRS", dir.join("system"));
+        env::set_var(
+            "DBUS_SESSION_BUS_ADDRESS",
+            format!("unix:path={}", dir.join("no-bus").display()),
+        );
+        env::remove_var("DARKMODE");
+        env::remove_var("GTK_THEME");
+        Self { dir }
+    }
+
+    /// Returns the path of `file` in `$XDG_CONFIG_HOME`.
+    pub fn config(&self, file: &str) -> PathBuf {
+        self.dir.join("config").join(file)
+    }
+
+    /// Returns the path of `file` in `$XDG_CONFIG_DIRS`.
+    pub fn system(&self, file: &str) -> PathBuf {
+        self.dir.join("system").join(file)
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Replaces the file at `path` by renaming, like dconf and most editors do.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, contents).unwrap();
+    fs::rename(tmp, path).unwrap();
+}
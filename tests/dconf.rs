@@ -0,0 +1,87 @@
+#![cfg(target_os = "linux")]
+
+use std::path::Path;
+use std::sync::mpsc;
+use std::{fs, io};
+
+use common::{write, Environment, TIMEOUT};
+use darkmode::{Backend, DconfBackend, Detector, Error, Mode};
+
+mod common;
+
+/// Writes a GVariant database holding only `color-scheme` set to `value`.
+fn write_database(path: &Path, value: &str) {
+    const DIR: &[u8] = b"/org/gnome/desktop/interface/";
+    const KEY: &[u8] = b"color-scheme";
+    // Header, root table without bloom filter and buckets, and two items.
+    let items = 24 + 8;
+    let strings = items + 2 * 24;
+    let variant = [value.as_bytes(), b"\0\0s"].concat();
+
+    let mut data = b"GVariant".to_vec();
+    for word in [0, 0, 24, strings] {
+        data.extend_from_slice(&u32::try_from(word).unwrap().to_le_bytes());
+    }
+    data.extend_from_slice(&[0; 8]);
+    let mut offset = strings + DIR.len() + KEY.len();
+    for (parent, key_start, key, kind, value) in [
+        (u32::MAX, strings, DIR, b'L', &[][..]),
+        (0, strings + DIR.len(), KEY, b'v', &variant[..]),
+    ] {
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&parent.to_le_bytes());
+        data.extend_from_slice(&u32::try_from(key_start).unwrap().to_le_bytes());
+        data.extend_from_slice(&u16::try_from(key.len()).unwrap().to_le_bytes());
+        data.extend_from_slice(&[kind, 0]);
+        data.extend_from_slice(&u32::try_from(offset).unwrap().to_le_bytes());
+        offset += value.len();
+        data.extend_from_slice(&u32::try_from(offset).unwrap().to_le_bytes());
+    }
+    data.extend_from_slice(DIR);
+    data.extend_from_slice(KEY);
+    data.extend_from_slice(&variant);
+    write(path, data);
+}
+
+#[test]
+fn fallback() {
+    let environment = Environment::new("dconf");
+    let database = environment.config("dconf/user");
+
+    assert!(matches!(
+        DconfBackend.detect(),
+        Err(Error::Config { path, .. }) if path == database
+    ));
+
+    // A corrupt database is skipped for the other fallbacks.
+    write(&database, "GVariant");
+    assert!(matches!(
+        DconfBackend.detect(),
+        Err(Error::Config { source, .. }) if source.kind() == io::ErrorKind::InvalidData
+    ));
+    let detector = Detector::default();
+    let kde = environment.config("kdeglobals");
+    write(&kde, "[Colors:Window]\nBackgroundNormal=#202326\n");
+    let detection = detector.detect().unwrap();
+    assert_eq!((detection.mode, detection.backend), (Mode::Dark, "kde"));
+    fs::remove_file(kde).unwrap();
+    let gtk = environment.config("gtk-3.0/settings.ini");
+    write(&gtk, "[Settings]\ngtk-theme-name=Adwaita-dark\n");
+    let detection = detector.detect().unwrap();
+    assert_eq!((detection.mode, detection.backend), (Mode::Dark, "gtk"));
+    fs::remove_file(gtk).unwrap();
+
+    write_database(&database, "prefer-dark");
+    assert_eq!(darkmode::detect().unwrap(), Mode::Dark);
+
+    let (sender, receiver) = mpsc::channel();
+    let subscription = darkmode::subscribe(move |mode| sender.send(mode).unwrap()).unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Dark);
+
+    write_database(&database, "prefer-light");
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Light);
+    write_database(&database, "default");
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Default);
+
+    subscription.unsubscribe().unwrap();
+}
@@ -0,0 +1,108 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+
+use darkmode::{Backend, Detection, Detector, Error, Mode, Subscription};
+
+struct Fixed(&'static str, fn() -> Result<Mode, Error>, Arc<AtomicBool>);
+
+impl Fixed {
+    fn new(name: &'static str, detect: fn() -> Result<Mode, Error>) -> Self {
+        Self(name, detect, Arc::default())
+    }
+}
+
+impl Backend for Fixed {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn detect(&self) -> Result<Mode, Error> {
+        (self.1)()
+    }
+
+    fn watch(&self, mut call_back: Box<dyn FnMut(Mode) + Send>) -> Result<Subscription, Error> {
+        call_back(self.detect()?);
+        let stopped = self.2.clone();
+        Ok(Subscription::from_stop(move || {
+            stopped.store(true, Ordering::Release);
+            Ok(())
+        }))
+    }
+}
+
+fn unavailable() -> Result<Mode, Error> {
+    Err(Error::BackendUnavailable("desktop not used".into()))
+}
+
+#[test]
+fn detect() {
+    let detector = Detector::new()
+        .backend(Fixed::new("default", || Ok(Mode::Default)))
+        .backend(Fixed::new("unavailable", unavailable))
+        .backend(Fixed::new("dark", || Ok(Mode::Dark)))
+        .backend(Fixed::new("light", || Ok(Mode::Light)));
+    assert_eq!(
+        format!("{detector:?}"),
+        r#"["default", "unavailable", "dark", "light"]"#
+    );
+    let Detection { mode, backend, .. } = detector.detect().unwrap();
+    assert_eq!((mode, backend), (Mode::Dark, "dark"));
+
+    let detector = Detector::new()
+        .backend(Fixed::new("unavailable", unavailable))
+        .backend(Fixed::new("default", || Ok(Mode::Default)));
+    let Detection { mode, backend, .. } = detector.detect().unwrap();
+    assert_eq!((mode, backend), (Mode::Default, "default"));
+
+    let detector = Detector::new()
+        .backend(Fixed::new("first", || {
+            Err(Error::NotFound {
+                namespace: "first".to_owned(),
+                key: "mode".to_owned(),
+            })
+        }))
+        .backend(Fixed::new("second", unavailable));
+    assert!(matches!(
+        detector.detect(),
+        Err(Error::NotFound { namespace, .. }) if namespace == "first"
+    ));
+
+    // Backends failing for any reason are skipped.
+    let detector = Detector::new()
+        .backend(Fixed::new("failing", || Err(Error::Disconnected)))
+        .backend(Fixed::new("dark", || Ok(Mode::Dark)));
+    let Detection { mode, backend, .. } = detector.detect().unwrap();
+    assert_eq!((mode, backend), (Mode::Dark, "dark"));
+    let detector = Detector::new()
+        .backend(Fixed::new("default", || Ok(Mode::Default)))
+        .backend(Fixed::new("failing", || Err(Error::Disconnected)));
+    let Detection { mode, backend, .. } = detector.detect().unwrap();
+    assert_eq!((mode, backend), (Mode::Default, "default"));
+
+    // An actual failure is reported over a backend not being available.
+    let detector = Detector::new()
+        .backend(Fixed::new("unavailable", unavailable))
+        .backend(Fixed::new("failing", || Err(Error::Disconnected)));
+    assert!(matches!(detector.detect(), Err(Error::Disconnected)));
+
+    assert!(matches!(Detector::new().detect(), Err(Error::NoBackend)));
+}
+
+#[test]
+fn subscribe() {
+    let light = Fixed::new("light", || Ok(Mode::Light));
+    let stopped = light.2.clone();
+    let detector = Detector::new()
+        .backend(Fixed::new("unavailable", unavailable))
+        .backend(Fixed::new("failing", || Err(Error::Disconnected)))
+        .backend(light);
+
+    let (sender, receiver) = mpsc::channel();
+    let subscription = detector
+        .subscribe(move |mode| sender.send(mode).unwrap())
+        .unwrap();
+    assert_eq!(receiver.try_recv().unwrap(), Mode::Light);
+    assert!(!stopped.load(Ordering::Acquire));
+    subscription.unsubscribe().unwrap();
+    assert!(stopped.load(Ordering::Acquire));
+}
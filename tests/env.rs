@@ -0,0 +1,57 @@
+use std::env;
+use std::sync::mpsc;
+
+use common::Environment;
+use darkmode::{Event, Hub, Mode, Subscription};
+
+mod common;
+
+#[test]
+fn override_mode() {
+    // Neither a bus nor any configuration, as the override applies before.
+    let _environment = Environment::new("env");
+    env::set_var("GTK_THEME", "Adwaita:dark");
+    env::set_var("DARKMODE", "light");
+    assert_eq!(darkmode::detect().unwrap(), Mode::Light);
+    env::set_var("DARKMODE", "Default");
+    assert_eq!(darkmode::detect().unwrap(), Mode::Default);
+    env::set_var("DARKMODE", "unknown");
+    assert_eq!(darkmode::detect().unwrap(), Mode::Dark);
+    env::remove_var("DARKMODE");
+    assert_eq!(darkmode::detect().unwrap(), Mode::Dark);
+
+    let (sender, receiver) = mpsc::channel();
+    let subscription = darkmode::subscribe(move |mode| sender.send(mode).unwrap()).unwrap();
+    assert_eq!(receiver.try_recv().unwrap(), Mode::Dark);
+    subscription.unsubscribe().unwrap();
+    // No further modes are delivered.
+    assert!(receiver.recv().is_err());
+
+    // Also when subscribing to the portal's events only.
+    let (sender, receiver) = mpsc::channel();
+    let subscription = Subscription::builder()
+        .subscribe_events(move |event| sender.send(event).unwrap())
+        .unwrap();
+    assert!(matches!(
+        receiver.try_recv().unwrap(),
+        Event::ModeChanged(Mode::Dark)
+    ));
+    subscription.unsubscribe().unwrap();
+    assert!(receiver.recv().is_err());
+
+    let hub = Hub::new().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    let _subscriber = hub.subscribe(move |mode| sender.send(mode).unwrap());
+    assert_eq!(receiver.try_recv().unwrap(), Mode::Dark);
+
+    #[cfg(all(feature = "stream", unix))]
+    {
+        use futures_lite::{future, StreamExt};
+
+        let mut stream = darkmode::stream().unwrap();
+        future::block_on(async {
+            assert_eq!(stream.next().await, Some(Mode::Dark));
+            assert!(future::poll_once(stream.next()).await.is_none());
+        });
+    }
+}
@@ -0,0 +1,47 @@
+#![cfg(target_os = "linux")]
+
+use std::sync::mpsc;
+
+use common::{write, Environment, TIMEOUT};
+use darkmode::Mode;
+
+mod common;
+
+#[test]
+fn fallback() {
+    let environment = Environment::new("gtk");
+    let gtk3 = environment.config("gtk-3.0/settings.ini");
+    let gtk4 = environment.config("gtk-4.0/settings.ini");
+
+    write(&gtk3, "[Settings]\ngtk-theme-name=Adwaita-dark\n");
+    assert_eq!(darkmode::detect().unwrap(), Mode::Dark);
+    // GTK 4's settings take precedence.
+    write(
+        &gtk4,
+        "[Settings]\ngtk-theme-name = Adwaita\ngtk-application-prefer-dark-theme = false\n",
+    );
+    // A light theme is no preference.
+    assert_eq!(darkmode::detect().unwrap(), Mode::Default);
+
+    let (sender, receiver) = mpsc::channel();
+    let subscription = darkmode::subscribe(move |mode| sender.send(mode).unwrap()).unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Default);
+
+    write(
+        &gtk4,
+        "[Settings]\ngtk-theme-name=Adwaita\ngtk-application-prefer-dark-theme=1\n",
+    );
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Dark);
+    subscription.unsubscribe().unwrap();
+
+    // The fallback is also used when the portal reports no preference.
+    #[cfg(feature = "mock")]
+    {
+        let portal = darkmode::mock::MockPortal::start().unwrap();
+        portal
+            .set("org.freedesktop.appearance", "color-scheme", Mode::Default)
+            .unwrap();
+        std::env::set_var("DBUS_SESSION_BUS_ADDRESS", portal.address());
+        assert_eq!(darkmode::detect().unwrap(), Mode::Dark);
+    }
+}
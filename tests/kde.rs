@@ -0,0 +1,47 @@
+#![cfg(target_os = "linux")]
+
+use std::sync::mpsc;
+
+use common::{write, Environment, TIMEOUT};
+use darkmode::{Backend, KdeBackend, Mode};
+
+mod common;
+
+#[test]
+fn fallback() {
+    let environment = Environment::new("kde");
+    let user = environment.config("kdeglobals");
+
+    write(&user, "[General]\nColorScheme=Custom\n");
+    // The name of a scheme is no preference unless it is dark.
+    assert_eq!(KdeBackend.detect().unwrap(), Mode::Default);
+    write(
+        &environment.system("kdeglobals"),
+        "[Colors:Window]\nBackgroundNormal=32,35,38\n\n[General]\nColorScheme=BreezeDark\n",
+    );
+    // The background of the system's configuration applies.
+    assert_eq!(darkmode::detect().unwrap(), Mode::Dark);
+
+    let (sender, receiver) = mpsc::channel();
+    let subscription = darkmode::subscribe(move |mode| sender.send(mode).unwrap()).unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Dark);
+
+    write(
+        &user,
+        "[General]\nColorScheme=Custom\n\n[Colors:Window]\nBackgroundNormal = 239,240,241\n",
+    );
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Light);
+    // KDE also writes colors in hexadecimal, with alpha first.
+    write(&user, "[Colors:Window]\nBackgroundNormal=#ff202326\n");
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Dark);
+    write(&user, "[Colors:Window]\nBackgroundNormal=#eff0f1\n");
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Light);
+    // A color that cannot be parsed is ignored, leaving the scheme's name.
+    write(
+        &user,
+        "[General]\nColorScheme=Custom\n\n[Colors:Window]\nBackgroundNormal=#20232\n",
+    );
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Default);
+
+    subscription.unsubscribe().unwrap();
+}
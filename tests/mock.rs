@@ -0,0 +1,280 @@
+#![cfg(target_os = "linux")]
+
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use darkmode::mock::MockPortal;
+use darkmode::{AccentColor, Detector, Error, Event, Mode, PortalBackend, Subscription, Value};
+
+const APPEARANCE: &str = "org.freedesktop.appearance";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn detect() {
+    let portal = MockPortal::start().unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Dark).unwrap();
+    assert_eq!(portal.client().unwrap().detect().unwrap(), Mode::Dark);
+}
+
+#[test]
+fn subscribe() {
+    let portal = MockPortal::start().unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let subscription = portal
+        .client()
+        .unwrap()
+        .subscribe(move |mode| sender.send(mode).unwrap())
+        .unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Light);
+
+    portal.set(APPEARANCE, "color-scheme", Mode::Dark).unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Dark);
+
+    subscription.unsubscribe().unwrap();
+}
+
+#[test]
+fn accent_color() {
+    let portal = MockPortal::start().unwrap();
+    let color = Value::Struct(vec![0.25.into(), 0.5.into(), 1.0.into()]);
+    portal.set(APPEARANCE, "accent-color", color).unwrap();
+    assert_eq!(
+        portal.client().unwrap().accent_color().unwrap(),
+        Some(AccentColor {
+            red: 0.25,
+            green: 0.5,
+            blue: 1.0
+        })
+    );
+}
+
+#[test]
+fn read_all() {
+    let portal = MockPortal::start().unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Dark).unwrap();
+    portal.set("org.example", "list", vec!["a", "b"]).unwrap();
+    let settings = portal.client().unwrap().settings();
+
+    let all = settings.read_all(&[]).unwrap();
+    assert_eq!(all.len(), 2);
+    assert_eq!(
+        all["org.example"]["list"],
+        Value::Array(vec!["a".into(), "b".into()])
+    );
+
+    let appearance = settings.read_all(&["org.freedesktop.*"]).unwrap();
+    assert_eq!(appearance.len(), 1);
+    assert_eq!(appearance[APPEARANCE]["color-scheme"], Value::U64(1));
+
+    portal.remove("org.example", "list");
+    assert!(settings.read_all(&["org.example"]).unwrap()["org.example"].is_empty());
+}
+
+macro_rules! assert_event {
+    ($receiver:expr, $pattern:pat) => {
+        let event = $receiver.recv_timeout(TIMEOUT).unwrap();
+        assert!(matches!(event, $pattern), "unexpected {event:?}");
+    };
+}
+
+#[test]
+fn portal_restart() {
+    let portal = MockPortal::start().unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let _subscription = portal
+        .client()
+        .unwrap()
+        .subscribe_events(move |event| sender.send(event).unwrap())
+        .unwrap();
+    assert_event!(receiver, Event::ModeChanged(Mode::Light));
+
+    portal.set_available(false).unwrap();
+    assert_event!(receiver, Event::PortalUnavailable);
+
+    // Not received while the portal is unavailable, but read when it is back.
+    portal.set(APPEARANCE, "color-scheme", Mode::Dark).unwrap();
+    portal.set_available(true).unwrap();
+    assert_event!(receiver, Event::ModeChanged(Mode::Dark));
+}
+
+#[test]
+fn bus_restart() {
+    let mut portal = MockPortal::start().unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let subscription = portal
+        .client()
+        .unwrap()
+        .subscribe_events(move |event| sender.send(event).unwrap())
+        .unwrap();
+    assert_event!(receiver, Event::ModeChanged(Mode::Light));
+
+    portal.set(APPEARANCE, "color-scheme", Mode::Dark).unwrap();
+    assert_event!(receiver, Event::ModeChanged(Mode::Dark));
+    portal.restart_bus().unwrap();
+    assert_event!(receiver, Event::Error(Error::Disconnected));
+    assert_event!(receiver, Event::Reconnected);
+    assert_event!(receiver, Event::ModeChanged(Mode::Dark));
+
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+    assert_event!(receiver, Event::ModeChanged(Mode::Light));
+
+    subscription.unsubscribe().unwrap();
+}
+
+#[test]
+fn error_event() {
+    let portal = MockPortal::start().unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let _subscription = portal
+        .client()
+        .unwrap()
+        .subscribe_events(move |event| sender.send(event).unwrap())
+        .unwrap();
+    assert_event!(receiver, Event::ModeChanged(Mode::Light));
+
+    portal.set(APPEARANCE, "color-scheme", "dark").unwrap();
+    assert_event!(
+        receiver,
+        Event::Error(Error::UnexpectedValue {
+            expected: "u32",
+            found: Some(Value::String(_)),
+        })
+    );
+
+    // The subscription keeps running.
+    portal.set(APPEARANCE, "color-scheme", Mode::Dark).unwrap();
+    assert_event!(receiver, Event::ModeChanged(Mode::Dark));
+}
+
+#[test]
+fn deduplicate() {
+    let portal = MockPortal::start().unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+    let client = portal.client().unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let _deduplicated = Subscription::builder()
+        .client(&client)
+        .subscribe(move |mode| sender.send(mode).unwrap())
+        .unwrap();
+    let (sender, all) = mpsc::channel();
+    let _all = Subscription::builder()
+        .client(&client)
+        .deduplicate(false)
+        .subscribe(move |mode| sender.send(mode).unwrap())
+        .unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Light);
+    assert_eq!(all.recv_timeout(TIMEOUT).unwrap(), Mode::Light);
+
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Dark).unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Dark).unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+    for mode in [Mode::Light, Mode::Dark, Mode::Dark, Mode::Light] {
+        assert_eq!(all.recv_timeout(TIMEOUT).unwrap(), mode);
+    }
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Dark);
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Light);
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn debounce() {
+    let portal = MockPortal::start().unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let _subscription = Subscription::builder()
+        .client(&portal.client().unwrap())
+        .debounce(Duration::from_millis(200))
+        .subscribe(move |mode| sender.send((mode, Instant::now())).unwrap())
+        .unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap().0, Mode::Light);
+
+    let start = Instant::now();
+    for mode in [Mode::Dark, Mode::Light, Mode::Default, Mode::Dark] {
+        portal.set(APPEARANCE, "color-scheme", mode).unwrap();
+    }
+    let (mode, delivered) = receiver.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(mode, Mode::Dark);
+    assert!(delivered - start >= Duration::from_millis(200));
+    assert!(receiver.recv_timeout(Duration::from_millis(400)).is_err());
+}
+
+#[test]
+fn watch() {
+    let portal = MockPortal::start().unwrap();
+    // Needs escaping in the match rule.
+    portal.set("org.example", "it's", 1).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let _subscription = portal
+        .client()
+        .unwrap()
+        .settings()
+        .watch("org.example", "it's", move |value: u32| {
+            sender.send(value).unwrap();
+        })
+        .unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), 1);
+
+    portal.set("org.example", "other", 2).unwrap();
+    portal.set("org.example", "it's", 3).unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), 3);
+}
+
+#[test]
+fn hub() {
+    let portal = MockPortal::start().unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+    let hub = Subscription::builder()
+        .client(&portal.client().unwrap())
+        .hub()
+        .unwrap();
+
+    let (sender, first) = mpsc::channel();
+    let first_subscriber = hub.subscribe(move |mode| sender.send(mode).unwrap());
+    let (sender, second) = mpsc::channel();
+    let _second_subscriber = hub.subscribe(move |mode| sender.send(mode).unwrap());
+    assert_eq!(first.recv_timeout(TIMEOUT).unwrap(), Mode::Light);
+    assert_eq!(second.recv_timeout(TIMEOUT).unwrap(), Mode::Light);
+    assert_eq!(hub.subscribers(), 2);
+
+    portal.set(APPEARANCE, "color-scheme", Mode::Dark).unwrap();
+    assert_eq!(first.recv_timeout(TIMEOUT).unwrap(), Mode::Dark);
+    assert_eq!(second.recv_timeout(TIMEOUT).unwrap(), Mode::Dark);
+    assert_eq!(hub.mode(), Mode::Dark);
+
+    first_subscriber.unsubscribe();
+    assert_eq!(hub.subscribers(), 1);
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+    assert_eq!(second.recv_timeout(TIMEOUT).unwrap(), Mode::Light);
+    assert!(first.try_recv().is_err());
+
+    hub.close().unwrap();
+}
+
+#[test]
+fn detector() {
+    let portal = MockPortal::start().unwrap();
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+    let detector = Detector::new().backend(PortalBackend::new().client(&portal.client().unwrap()));
+    let detection = detector.detect().unwrap();
+    assert_eq!((detection.mode, detection.backend), (Mode::Light, "portal"));
+
+    let (sender, receiver) = mpsc::channel();
+    let _subscription = detector
+        .subscribe(move |mode| sender.send(mode).unwrap())
+        .unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Light);
+    portal.set(APPEARANCE, "color-scheme", Mode::Dark).unwrap();
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), Mode::Dark);
+}
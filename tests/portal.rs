@@ -0,0 +1,121 @@
+#![cfg(target_os = "linux")]
+
+use darkmode::mock::MockPortal;
+use darkmode::{AccentColor, Appearance, Client, Contrast, Error, Mode, Value};
+
+const APPEARANCE: &str = "org.freedesktop.appearance";
+
+fn portal(version: u32) -> MockPortal {
+    let portal = MockPortal::start().unwrap();
+    portal.set_version(version);
+    portal
+}
+
+#[test]
+fn version_2() {
+    let portal = portal(2);
+    portal.set(APPEARANCE, "color-scheme", Mode::Dark).unwrap();
+    assert_eq!(portal.client().unwrap().detect().unwrap(), Mode::Dark);
+}
+
+#[test]
+fn version_1() {
+    let portal = portal(1);
+    portal.set(APPEARANCE, "color-scheme", Mode::Light).unwrap();
+    let color = Value::Struct(vec![0.0.into(), 0.5.into(), 1.0.into()]);
+    portal.set(APPEARANCE, "accent-color", color).unwrap();
+
+    let client = portal.client().unwrap();
+    assert_eq!(client.detect().unwrap(), Mode::Light);
+    assert_eq!(
+        client.accent_color().unwrap(),
+        Some(AccentColor {
+            red: 0.0,
+            green: 0.5,
+            blue: 1.0
+        })
+    );
+    assert_eq!(
+        client
+            .settings()
+            .read_one::<u32>(APPEARANCE, "color-scheme")
+            .unwrap(),
+        2
+    );
+}
+
+#[test]
+fn missing_key() {
+    for version in [1, 2] {
+        let portal = portal(version);
+        let error = portal.client().unwrap().detect().unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                Error::NotFound { namespace, key }
+                    if namespace == APPEARANCE && key == "color-scheme"
+            ),
+            "version {version}: {error:?}"
+        );
+    }
+}
+
+#[test]
+fn missing_keys_in_appearance() {
+    let portal = portal(2);
+    portal.set(APPEARANCE, "contrast", 1u32).unwrap();
+    assert_eq!(portal.client().unwrap().appearance().unwrap(), Appearance {
+        contrast: Contrast::High,
+        ..Appearance::default()
+    });
+}
+
+#[test]
+fn wrong_type() {
+    for version in [1, 2] {
+        let portal = portal(version);
+        portal.set(APPEARANCE, "color-scheme", "dark").unwrap();
+        let error = portal.client().unwrap().detect().unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                Error::UnexpectedValue {
+                    expected: "u32",
+                    found: Some(Value::String(found)),
+                } if found == "dark"
+            ),
+            "version {version}: {error:?}"
+        );
+    }
+}
+
+#[test]
+fn absent_portal() {
+    let portal = portal(2);
+    portal.set(APPEARANCE, "color-scheme", Mode::Dark).unwrap();
+    portal.set_available(false).unwrap();
+
+    let client = portal.client().unwrap();
+    assert!(matches!(client.detect(), Err(Error::PortalUnavailable(_))));
+    assert!(matches!(
+        client.appearance(),
+        Err(Error::PortalUnavailable(_))
+    ));
+    assert!(matches!(
+        client.subscribe(|_| {}),
+        Err(Error::PortalUnavailable(_))
+    ));
+
+    portal.set_available(true).unwrap();
+    assert_eq!(client.detect().unwrap(), Mode::Dark);
+}
+
+#[test]
+fn absent_bus() {
+    let error = Client::builder()
+        .address("unix:path=/nonexistent/bus")
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(error, Error::NoSessionBus(_)), "{error:?}");
+}